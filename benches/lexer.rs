@@ -0,0 +1,34 @@
+// lexing so fast, nobody has ever seen anything like it
+
+use std::time::Instant;
+use trump::lexer::Lexer;
+
+const SNIPPET: &str = r#"/// a very stable genius
+make größe = (5 + 5 * 2 - 3 / 4 ^ 2 % 10) >= 2.5 && !fake_news;
+make greeting = "Hello, {größe}! \u{1F1FA}\u{1F1F8}"; /* tremendous /* nested */ comment */
+"#;
+
+/// Lexes a generated source of roughly `target_bytes` and reports the throughput. Doubling
+/// the input should roughly double the time if lexing is linear.
+fn bench(target_bytes: usize) {
+    let source = SNIPPET.repeat(target_bytes / SNIPPET.len() + 1);
+
+    let start = Instant::now();
+    let tokens = Lexer::new(&source).count();
+    let elapsed = start.elapsed();
+
+    let megabytes = source.len() as f64 / (1024.0 * 1024.0);
+    println!(
+        "{:>6.2} MiB, {:>9} tokens in {:>8.2?} ({:.1} MiB/s)",
+        megabytes,
+        tokens,
+        elapsed,
+        megabytes / elapsed.as_secs_f64()
+    );
+}
+
+fn main() {
+    for megabytes in [1, 2, 4, 8] {
+        bench(megabytes * 1024 * 1024);
+    }
+}
@@ -1,264 +1,1904 @@
-use serde::Serialize;
-use std::collections::HashMap;
-
-#[derive(Debug, Clone, PartialEq, Serialize)]
-pub enum NodeType {
-    Program,
-    Statement,
-    Expression,
-    ExpressionStatement,
-    InfixStatement,
-    IntegerLiteral,
-    FloatLiteral,
-}
-
-impl NodeType {
-    pub fn value(&self) -> &'static str {
-        match self {
-            NodeType::Program => "Program",
-            NodeType::Statement => "Statement",
-            NodeType::Expression => "Expression",
-            NodeType::ExpressionStatement => "ExpressionStatement",
-            NodeType::InfixStatement => "InfixStatement",
-            NodeType::IntegerLiteral => "IntegerLiteral",
-            NodeType::FloatLiteral => "FloatLiteral",
+use crate::token::{Span, TokenType};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+
+/// The version of the JSON AST format `Program::to_json` writes and `Program::from_json`
+/// accepts. It goes up whenever the shape of a node changes.
+pub const AST_FORMAT_VERSION: u64 = 1;
+
+/// Identifies a node within the program it was parsed into. The parser numbers nodes in
+/// the order it finishes them, so parsing the same source always gives the same ids.
+#[derive(Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Clone, Copy, Serialize, Deserialize)]
+pub struct NodeId(pub u32);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Program {
+    pub id: NodeId,
+    pub span: Span, // The whole source
+    pub statements: Vec<Statement>,
+}
+
+impl Program {
+    pub fn new(id: NodeId, span: Span, statements: Vec<Statement>) -> Self {
+        Program {
+            id,
+            span,
+            statements,
+        }
+    }
+
+    /// Writes the program, spans and ids included, as a JSON document of the form
+    /// `{"version": 1, "program": {...}}` that `from_json` reads back into the same program.
+    pub fn to_json(&self) -> String {
+        let document = AstDocument {
+            version: AST_FORMAT_VERSION,
+            program: self,
+        };
+        // Not pretty-printed, since indenting every level makes the output grow with the
+        // square of how deeply the program nests
+        serde_json::to_string(&document).expect("every AST can be written as JSON")
+    }
+
+    /// Reads a program written by `to_json`, so tools can hand the compiler an AST
+    /// without going through the source.
+    pub fn from_json(json: &str) -> Result<Program, AstJsonError> {
+        // Check the version first, so a newer AST is reported as such rather than as malformed
+        let header: AstHeader = Self::_read_json(json)?;
+        let version = header.version.ok_or(AstJsonError::MissingVersion)?;
+        if version != AST_FORMAT_VERSION {
+            return Err(AstJsonError::UnsupportedVersion(version));
         }
+
+        let body: AstBody = Self::_read_json(json)?;
+        body.program.ok_or(AstJsonError::MissingProgram)
+    }
+
+    /// Deserializes a whole JSON document with no limit on how deeply it nests, since
+    /// every operator in a long chain like `1 + 1 + ... + 1` adds a level. The stack is
+    /// grown as needed instead.
+    fn _read_json<T: DeserializeOwned>(json: &str) -> Result<T, AstJsonError> {
+        let mut deserializer = serde_json::Deserializer::from_str(json);
+        deserializer.disable_recursion_limit();
+
+        let value = T::deserialize(serde_stacker::Deserializer::new(&mut deserializer))
+            .map_err(AstJsonError::Malformed)?;
+        deserializer.end().map_err(AstJsonError::Malformed)?;
+        Ok(value)
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
-pub enum Node {
-    Program(Program),
-    Statement(Statement),
-    Expression(Expression),
-    ExpressionStatement(ExpressionStatement),
-    InfixExpression(InfixExpression),
-    IntegerLiteral(IntegerLiteral),
-    FloatLiteral(FloatLiteral),
+/// Runs part of (de)serializing a node that can contain itself, moving to a new stack when
+/// this one runs low, so deeply nested programs don't overflow it. serde reads a node tagged
+/// with `type` into a buffer first, and reading the buffer back doesn't go through the
+/// `serde_stacker` deserializer `from_json` sets up.
+///
+/// The recursive enums derive with `remote = "Self"`, which makes the derived code inherent
+/// `serialize` and `deserialize` functions that their `Serialize` and `Deserialize` impls
+/// call through this.
+fn nested<T>(f: impl FnOnce() -> T) -> T {
+    stacker::maybe_grow(64 * 1024, 2 * 1024 * 1024, f)
+}
+
+#[derive(Serialize)]
+struct AstDocument<'a> {
+    version: u64,
+    program: &'a Program,
 }
 
-impl Node {
-    pub fn get_type(&self) -> NodeType {
+#[derive(Deserialize)]
+struct AstHeader {
+    version: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct AstBody {
+    program: Option<Program>,
+}
+
+#[derive(Debug)]
+pub enum AstJsonError {
+    Malformed(serde_json::Error), // Not JSON, or a node isn't shaped the way it should be
+    MissingVersion,
+    UnsupportedVersion(u64),
+    MissingProgram,
+}
+
+impl fmt::Display for AstJsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Node::Program(_) => NodeType::Program,
-            Node::Statement(_) => NodeType::Statement,
-            Node::Expression(_) => NodeType::Expression,
-            Node::ExpressionStatement(_) => NodeType::ExpressionStatement,
-            Node::InfixExpression(_) => NodeType::InfixStatement,
-            Node::IntegerLiteral(_) => NodeType::IntegerLiteral,
-            Node::FloatLiteral(_) => NodeType::FloatLiteral,
+            AstJsonError::Malformed(error) => write!(f, "Malformed AST: {}", error),
+            AstJsonError::MissingVersion => write!(f, "The AST has no format version"),
+            AstJsonError::UnsupportedVersion(version) => write!(
+                f,
+                "AST format version {} is not supported, expected {}",
+                version, AST_FORMAT_VERSION
+            ),
+            AstJsonError::MissingProgram => write!(f, "The AST has no program"),
         }
     }
+}
 
-    pub fn json(&self) -> HashMap<String, serde_json::Value> {
-        match self {
-            Node::Program(program) => program.json(),
-            Node::Statement(stmt) => stmt.json(),
-            Node::Expression(expr) => expr.json(),
-            Node::ExpressionStatement(expr_stmt) => expr_stmt.json(),
-            Node::InfixExpression(infix_expr) => infix_expr.json(),
-            Node::IntegerLiteral(int_lit) => int_lit.json(),
-            Node::FloatLiteral(float_lit) => float_lit.json(),
+impl std::error::Error for AstJsonError {}
+
+/// Prints one statement per line with every prefix and infix expression parenthesized,
+/// which shows exactly how the parser grouped operators.
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for stmt in &self.statements {
+            writeln!(f, "{}", stmt)?;
         }
+        Ok(())
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
-pub struct Program {
-    pub statements: Vec<Node>,
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(remote = "Self", tag = "type")]
+pub enum Statement {
+    ExpressionStatement(ExpressionStatement),
+    LetStatement(LetStatement),
+    BlockStatement(BlockStatement),
+    FunctionStatement(FunctionStatement),
+    ReturnStatement(ReturnStatement),
+    WhileStatement(WhileStatement),
+    ForStatement(ForStatement),
+    BreakStatement(BreakStatement),
+    ContinueStatement(ContinueStatement),
 }
 
-impl Program {
-    pub fn new() -> Self {
-        Program {
-            statements: Vec::new(),
-        }
+impl Serialize for Statement {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        nested(|| Statement::serialize(self, serializer))
     }
+}
 
-    pub fn add_statement(&mut self, statement: Node) {
-        self.statements.push(statement);
+impl<'de> Deserialize<'de> for Statement {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        nested(|| Statement::deserialize(deserializer))
     }
+}
 
-    pub fn json(&self) -> HashMap<String, serde_json::Value> {
-        let mut map = HashMap::new();
-        map.insert(
-            "type".to_string(),
-            serde_json::Value::String(NodeType::Program.value().to_string()),
-        );
+impl Statement {
+    pub fn id(&self) -> NodeId {
+        match self {
+            Statement::ExpressionStatement(expr_stmt) => expr_stmt.id,
+            Statement::LetStatement(let_stmt) => let_stmt.id,
+            Statement::BlockStatement(block) => block.id,
+            Statement::FunctionStatement(function) => function.id,
+            Statement::ReturnStatement(return_stmt) => return_stmt.id,
+            Statement::WhileStatement(while_stmt) => while_stmt.id,
+            Statement::ForStatement(for_stmt) => for_stmt.id,
+            Statement::BreakStatement(break_stmt) => break_stmt.id,
+            Statement::ContinueStatement(continue_stmt) => continue_stmt.id,
+        }
+    }
 
-        let statements_json: Vec<serde_json::Value> = self
-            .statements
-            .iter()
-            .map(|stmt| {
-                let mut stmt_map = HashMap::new();
-                stmt_map.insert(
-                    stmt.get_type().value().to_string(),
-                    serde_json::to_value(stmt.json()).unwrap(),
-                );
-                serde_json::Value::Object(stmt_map.into_iter().collect())
-            })
-            .collect();
+    pub fn span(&self) -> Span {
+        match self {
+            Statement::ExpressionStatement(expr_stmt) => expr_stmt.span,
+            Statement::LetStatement(let_stmt) => let_stmt.span,
+            Statement::BlockStatement(block) => block.span,
+            Statement::FunctionStatement(function) => function.span,
+            Statement::ReturnStatement(return_stmt) => return_stmt.span,
+            Statement::WhileStatement(while_stmt) => while_stmt.span,
+            Statement::ForStatement(for_stmt) => for_stmt.span,
+            Statement::BreakStatement(break_stmt) => break_stmt.span,
+            Statement::ContinueStatement(continue_stmt) => continue_stmt.span,
+        }
+    }
+}
 
-        map.insert(
-            "statements".to_string(),
-            serde_json::Value::Array(statements_json),
-        );
-        map
+impl fmt::Display for Statement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Statement::ExpressionStatement(expr_stmt) => expr_stmt.fmt(f),
+            Statement::LetStatement(let_stmt) => let_stmt.fmt(f),
+            Statement::BlockStatement(block) => block.fmt(f),
+            Statement::FunctionStatement(function) => function.fmt(f),
+            Statement::ReturnStatement(return_stmt) => return_stmt.fmt(f),
+            Statement::WhileStatement(while_stmt) => while_stmt.fmt(f),
+            Statement::ForStatement(for_stmt) => for_stmt.fmt(f),
+            Statement::BreakStatement(break_stmt) => break_stmt.fmt(f),
+            Statement::ContinueStatement(continue_stmt) => continue_stmt.fmt(f),
+        }
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ExpressionStatement {
-    pub expr: Box<Node>,
+    pub id: NodeId,
+    pub span: Span,
+    pub expr: Box<Expression>,
 }
 
 impl ExpressionStatement {
-    pub fn new(expr: Node) -> Self {
+    pub fn new(id: NodeId, span: Span, expr: Expression) -> Self {
         ExpressionStatement {
+            id,
+            span,
             expr: Box::new(expr),
         }
     }
+}
 
-    pub fn json(&self) -> HashMap<String, serde_json::Value> {
-        let mut map = HashMap::new();
-        map.insert(
-            "type".to_string(),
-            serde_json::Value::String(NodeType::ExpressionStatement.value().to_string()),
-        );
-        map.insert(
-            "expression".to_string(),
-            serde_json::to_value(self.expr.json()).unwrap(),
-        );
-        map
+impl fmt::Display for ExpressionStatement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{};", self.expr)
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
-pub struct InfixExpression {
-    pub left_node: Box<Node>,
-    pub operator: String,
-    pub right_node: Box<Node>,
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LetStatement {
+    pub id: NodeId,
+    pub span: Span,
+    pub name: Identifier,
+    pub value: Box<Expression>,
 }
 
-impl InfixExpression {
-    pub fn new(left_node: Node, operator: String, right_node: Node) -> Self {
-        InfixExpression {
-            left_node: Box::new(left_node),
-            operator,
-            right_node: Box::new(right_node),
+impl LetStatement {
+    pub fn new(id: NodeId, span: Span, name: Identifier, value: Expression) -> Self {
+        LetStatement {
+            id,
+            span,
+            name,
+            value: Box::new(value),
         }
     }
+}
 
-    pub fn json(&self) -> HashMap<String, serde_json::Value> {
-        let mut map = HashMap::new();
-        map.insert(
-            "type".to_string(),
-            serde_json::Value::String(NodeType::InfixStatement.value().to_string()),
-        );
-        map.insert(
-            "left_node".to_string(),
-            serde_json::to_value(self.left_node.json()).unwrap(),
-        );
-        map.insert(
-            "operator".to_string(),
-            serde_json::Value::String(self.operator.clone()),
-        );
-        map.insert(
-            "right_node".to_string(),
-            serde_json::to_value(self.right_node.json()).unwrap(),
-        );
-        map
+impl fmt::Display for LetStatement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "make {} = {};", self.name, self.value)
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
-pub struct IntegerLiteral {
-    pub value: i64,
+/// A `{ ... }` list of statements. On its own, or as a branch of an `if`, it opens a new
+/// scope and evaluates to the value of its last statement.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockStatement {
+    pub id: NodeId,
+    pub span: Span, // From the `{` to the `}`
+    pub statements: Vec<Statement>,
 }
 
-impl IntegerLiteral {
-    pub fn new(value: i64) -> Self {
-        IntegerLiteral { value }
+impl BlockStatement {
+    pub fn new(id: NodeId, span: Span, statements: Vec<Statement>) -> Self {
+        BlockStatement {
+            id,
+            span,
+            statements,
+        }
     }
+}
 
-    pub fn json(&self) -> HashMap<String, serde_json::Value> {
-        let mut map = HashMap::new();
-        map.insert(
-            "type".to_string(),
-            serde_json::Value::String(NodeType::IntegerLiteral.value().to_string()),
-        );
-        map.insert(
-            "value".to_string(),
-            serde_json::Value::Number(serde_json::Number::from(self.value)),
-        );
-        map
+impl fmt::Display for BlockStatement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{{")?;
+        for stmt in &self.statements {
+            write!(f, " {}", stmt)?;
+        }
+        write!(f, " }}")
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
-pub enum Statement {
-    ExpressionStatement(ExpressionStatement),
+/// `deal name(a, b) { ... }` declares a function and binds it to `name` in the current scope.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FunctionStatement {
+    pub id: NodeId,
+    pub span: Span,
+    pub name: Identifier,
+    pub parameters: Vec<Identifier>,
+    pub body: BlockStatement,
+}
+
+impl FunctionStatement {
+    pub fn new(
+        id: NodeId,
+        span: Span,
+        name: Identifier,
+        parameters: Vec<Identifier>,
+        body: BlockStatement,
+    ) -> Self {
+        FunctionStatement {
+            id,
+            span,
+            name,
+            parameters,
+            body,
+        }
+    }
+}
+
+impl fmt::Display for FunctionStatement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parameters: Vec<String> = self.parameters.iter().map(|p| p.to_string()).collect();
+        write!(
+            f,
+            "deal {}({}) {}",
+            self.name,
+            parameters.join(", "),
+            self.body
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReturnStatement {
+    pub id: NodeId,
+    pub span: Span,
+    pub value: Option<Box<Expression>>, // `None` for a bare `return;`
+}
+
+impl ReturnStatement {
+    pub fn new(id: NodeId, span: Span, value: Option<Expression>) -> Self {
+        ReturnStatement {
+            id,
+            span,
+            value: value.map(Box::new),
+        }
+    }
+}
+
+impl fmt::Display for ReturnStatement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.value {
+            Some(ref value) => write!(f, "return {};", value),
+            None => write!(f, "return;"),
+        }
+    }
+}
+
+fn write_label(f: &mut fmt::Formatter<'_>, label: &Option<Identifier>) -> fmt::Result {
+    match label {
+        Some(label) => write!(f, "{}: ", label),
+        None => Ok(()),
+    }
+}
+
+/// `while condition { ... }`, optionally labelled as in `outer: while ...`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WhileStatement {
+    pub id: NodeId,
+    pub span: Span, // Starts at the label, if there is one
+    pub label: Option<Identifier>,
+    pub condition: Box<Expression>,
+    pub body: BlockStatement,
+}
+
+impl WhileStatement {
+    pub fn new(
+        id: NodeId,
+        span: Span,
+        label: Option<Identifier>,
+        condition: Expression,
+        body: BlockStatement,
+    ) -> Self {
+        WhileStatement {
+            id,
+            span,
+            label,
+            condition: Box::new(condition),
+            body,
+        }
+    }
+}
+
+impl fmt::Display for WhileStatement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_label(f, &self.label)?;
+        write!(f, "while {} {}", self.condition, self.body)
+    }
+}
+
+/// `for variable in start..end { ... }`, counting from `start` up to but not including `end`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ForStatement {
+    pub id: NodeId,
+    pub span: Span, // Starts at the label, if there is one
+    pub label: Option<Identifier>,
+    pub variable: Identifier,
+    pub start: Box<Expression>,
+    pub end: Box<Expression>,
+    pub body: BlockStatement,
+}
+
+impl ForStatement {
+    pub fn new(
+        id: NodeId,
+        span: Span,
+        label: Option<Identifier>,
+        variable: Identifier,
+        start: Expression,
+        end: Expression,
+        body: BlockStatement,
+    ) -> Self {
+        ForStatement {
+            id,
+            span,
+            label,
+            variable,
+            start: Box::new(start),
+            end: Box::new(end),
+            body,
+        }
+    }
+}
+
+impl fmt::Display for ForStatement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_label(f, &self.label)?;
+        write!(
+            f,
+            "for {} in {}..{} {}",
+            self.variable, self.start, self.end, self.body
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BreakStatement {
+    pub id: NodeId,
+    pub span: Span,
+    pub label: Option<Identifier>, // `None` breaks out of the innermost loop
+}
+
+impl BreakStatement {
+    pub fn new(id: NodeId, span: Span, label: Option<Identifier>) -> Self {
+        BreakStatement { id, span, label }
+    }
+}
+
+impl fmt::Display for BreakStatement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.label {
+            Some(ref label) => write!(f, "break {};", label),
+            None => write!(f, "break;"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContinueStatement {
+    pub id: NodeId,
+    pub span: Span,
+    pub label: Option<Identifier>, // `None` continues the innermost loop
+}
+
+impl ContinueStatement {
+    pub fn new(id: NodeId, span: Span, label: Option<Identifier>) -> Self {
+        ContinueStatement { id, span, label }
+    }
+}
+
+impl fmt::Display for ContinueStatement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.label {
+            Some(ref label) => write!(f, "continue {};", label),
+            None => write!(f, "continue;"),
+        }
+    }
+}
+
+/// The operator of a `PrefixExpression`, written in JSON as its symbol.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Serialize, Deserialize)]
+pub enum UnaryOp {
+    #[serde(rename = "-")]
+    Negate,
+    #[serde(rename = "!")]
+    Not,
+}
+
+impl UnaryOp {
+    /// Maps a token to the prefix operator it spells, if it spells one.
+    pub fn from_token_type(token_type: TokenType) -> Option<Self> {
+        match token_type {
+            TokenType::Minus => Some(UnaryOp::Negate),
+            TokenType::Bang => Some(UnaryOp::Not),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UnaryOp::Negate => "-",
+            UnaryOp::Not => "!",
+        }
+    }
+}
+
+impl fmt::Display for UnaryOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The operator of an `InfixExpression`, written in JSON as its symbol.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Serialize, Deserialize)]
+pub enum BinaryOp {
+    #[serde(rename = "+")]
+    Add,
+    #[serde(rename = "-")]
+    Subtract,
+    #[serde(rename = "*")]
+    Multiply,
+    #[serde(rename = "/")]
+    Divide,
+    #[serde(rename = "%")]
+    Modulo,
+    #[serde(rename = "^")]
+    Power,
+    #[serde(rename = "==")]
+    Equal,
+    #[serde(rename = "!=")]
+    NotEqual,
+    #[serde(rename = "<")]
+    LessThan,
+    #[serde(rename = ">")]
+    GreaterThan,
+    #[serde(rename = "<=")]
+    LessEqual,
+    #[serde(rename = ">=")]
+    GreaterEqual,
+    #[serde(rename = "&&")]
+    And,
+    #[serde(rename = "||")]
+    Or,
+}
+
+impl BinaryOp {
+    /// Maps a token to the infix operator it spells, if it spells one.
+    pub fn from_token_type(token_type: TokenType) -> Option<Self> {
+        match token_type {
+            TokenType::Plus => Some(BinaryOp::Add),
+            TokenType::Minus => Some(BinaryOp::Subtract),
+            TokenType::Asterisk => Some(BinaryOp::Multiply),
+            TokenType::Slash => Some(BinaryOp::Divide),
+            TokenType::Modulus => Some(BinaryOp::Modulo),
+            TokenType::Pow => Some(BinaryOp::Power),
+            TokenType::Equal => Some(BinaryOp::Equal),
+            TokenType::NotEqual => Some(BinaryOp::NotEqual),
+            TokenType::LessThan => Some(BinaryOp::LessThan),
+            TokenType::GreaterThan => Some(BinaryOp::GreaterThan),
+            TokenType::LessEqual => Some(BinaryOp::LessEqual),
+            TokenType::GreaterEqual => Some(BinaryOp::GreaterEqual),
+            TokenType::And => Some(BinaryOp::And),
+            TokenType::Or => Some(BinaryOp::Or),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Subtract => "-",
+            BinaryOp::Multiply => "*",
+            BinaryOp::Divide => "/",
+            BinaryOp::Modulo => "%",
+            BinaryOp::Power => "^",
+            BinaryOp::Equal => "==",
+            BinaryOp::NotEqual => "!=",
+            BinaryOp::LessThan => "<",
+            BinaryOp::GreaterThan => ">",
+            BinaryOp::LessEqual => "<=",
+            BinaryOp::GreaterEqual => ">=",
+            BinaryOp::And => "&&",
+            BinaryOp::Or => "||",
+        }
+    }
+}
+
+impl fmt::Display for BinaryOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The operator of a compound assignment like `+=`. Only the arithmetic operators have
+/// one, so there's no `&&=` or `<=` assignment to represent. It's written in JSON as the
+/// symbol of the operator it applies, like `+` for `+=`.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Serialize, Deserialize)]
+pub enum AssignOp {
+    #[serde(rename = "+")]
+    Add,
+    #[serde(rename = "-")]
+    Subtract,
+    #[serde(rename = "*")]
+    Multiply,
+    #[serde(rename = "/")]
+    Divide,
+    #[serde(rename = "%")]
+    Modulo,
+    #[serde(rename = "^")]
+    Power,
+}
+
+impl AssignOp {
+    /// Maps a token to the compound assignment it spells, if it spells one.
+    pub fn from_token_type(token_type: TokenType) -> Option<Self> {
+        match token_type {
+            TokenType::PlusAssign => Some(AssignOp::Add),
+            TokenType::MinusAssign => Some(AssignOp::Subtract),
+            TokenType::AsteriskAssign => Some(AssignOp::Multiply),
+            TokenType::SlashAssign => Some(AssignOp::Divide),
+            TokenType::ModulusAssign => Some(AssignOp::Modulo),
+            TokenType::PowAssign => Some(AssignOp::Power),
+            _ => None,
+        }
+    }
+
+    /// The infix operator this applies, like `Add` for `+=`.
+    pub fn binary_op(&self) -> BinaryOp {
+        match self {
+            AssignOp::Add => BinaryOp::Add,
+            AssignOp::Subtract => BinaryOp::Subtract,
+            AssignOp::Multiply => BinaryOp::Multiply,
+            AssignOp::Divide => BinaryOp::Divide,
+            AssignOp::Modulo => BinaryOp::Modulo,
+            AssignOp::Power => BinaryOp::Power,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AssignOp::Add => "+=",
+            AssignOp::Subtract => "-=",
+            AssignOp::Multiply => "*=",
+            AssignOp::Divide => "/=",
+            AssignOp::Modulo => "%=",
+            AssignOp::Power => "^=",
+        }
+    }
+}
+
+impl fmt::Display for AssignOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(remote = "Self", tag = "type")]
 pub enum Expression {
+    PrefixExpression(PrefixExpression),
     InfixExpression(InfixExpression),
+    CallExpression(CallExpression),
+    IfExpression(IfExpression),
+    IndexExpression(IndexExpression),
+    AssignExpression(AssignExpression),
+    Identifier(Identifier),
     IntegerLiteral(IntegerLiteral),
     FloatLiteral(FloatLiteral),
+    BooleanLiteral(BooleanLiteral),
+    StringLiteral(StringLiteral),
+    StringInterpolation(StringInterpolation),
+    ArrayLiteral(ArrayLiteral),
+    MapLiteral(MapLiteral),
+}
+
+impl Serialize for Expression {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        nested(|| Expression::serialize(self, serializer))
+    }
+}
+
+impl<'de> Deserialize<'de> for Expression {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        nested(|| Expression::deserialize(deserializer))
+    }
 }
 
 impl Expression {
-    pub fn get_type(&self) -> NodeType {
+    pub fn id(&self) -> NodeId {
+        match self {
+            Expression::PrefixExpression(prefix_expr) => prefix_expr.id,
+            Expression::InfixExpression(infix_expr) => infix_expr.id,
+            Expression::CallExpression(call_expr) => call_expr.id,
+            Expression::IfExpression(if_expr) => if_expr.id,
+            Expression::IndexExpression(index_expr) => index_expr.id,
+            Expression::AssignExpression(assign_expr) => assign_expr.id,
+            Expression::Identifier(ident) => ident.id,
+            Expression::IntegerLiteral(int_lit) => int_lit.id,
+            Expression::FloatLiteral(float_lit) => float_lit.id,
+            Expression::BooleanLiteral(bool_lit) => bool_lit.id,
+            Expression::StringLiteral(string_lit) => string_lit.id,
+            Expression::StringInterpolation(interpolation) => interpolation.id,
+            Expression::ArrayLiteral(array_lit) => array_lit.id,
+            Expression::MapLiteral(map_lit) => map_lit.id,
+        }
+    }
+
+    pub fn span(&self) -> Span {
         match self {
-            Expression::InfixExpression(_) => NodeType::InfixStatement,
-            Expression::IntegerLiteral(_) => NodeType::IntegerLiteral,
-            Expression::FloatLiteral(_) => NodeType::FloatLiteral,
+            Expression::PrefixExpression(prefix_expr) => prefix_expr.span,
+            Expression::InfixExpression(infix_expr) => infix_expr.span,
+            Expression::CallExpression(call_expr) => call_expr.span,
+            Expression::IfExpression(if_expr) => if_expr.span,
+            Expression::IndexExpression(index_expr) => index_expr.span,
+            Expression::AssignExpression(assign_expr) => assign_expr.span,
+            Expression::Identifier(ident) => ident.span,
+            Expression::IntegerLiteral(int_lit) => int_lit.span,
+            Expression::FloatLiteral(float_lit) => float_lit.span,
+            Expression::BooleanLiteral(bool_lit) => bool_lit.span,
+            Expression::StringLiteral(string_lit) => string_lit.span,
+            Expression::StringInterpolation(interpolation) => interpolation.span,
+            Expression::ArrayLiteral(array_lit) => array_lit.span,
+            Expression::MapLiteral(map_lit) => map_lit.span,
         }
     }
 
-    pub fn json(&self) -> HashMap<String, serde_json::Value> {
+    pub fn span_mut(&mut self) -> &mut Span {
         match self {
-            Expression::InfixExpression(infix_expr) => infix_expr.json(),
-            Expression::IntegerLiteral(int_lit) => int_lit.json(),
-            Expression::FloatLiteral(float_lit) => float_lit.json(),
+            Expression::PrefixExpression(prefix_expr) => &mut prefix_expr.span,
+            Expression::InfixExpression(infix_expr) => &mut infix_expr.span,
+            Expression::CallExpression(call_expr) => &mut call_expr.span,
+            Expression::IfExpression(if_expr) => &mut if_expr.span,
+            Expression::IndexExpression(index_expr) => &mut index_expr.span,
+            Expression::AssignExpression(assign_expr) => &mut assign_expr.span,
+            Expression::Identifier(ident) => &mut ident.span,
+            Expression::IntegerLiteral(int_lit) => &mut int_lit.span,
+            Expression::FloatLiteral(float_lit) => &mut float_lit.span,
+            Expression::BooleanLiteral(bool_lit) => &mut bool_lit.span,
+            Expression::StringLiteral(string_lit) => &mut string_lit.span,
+            Expression::StringInterpolation(interpolation) => &mut interpolation.span,
+            Expression::ArrayLiteral(array_lit) => &mut array_lit.span,
+            Expression::MapLiteral(map_lit) => &mut map_lit.span,
         }
     }
 }
 
-impl Statement {
-    pub fn get_type(&self) -> NodeType {
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expression::PrefixExpression(prefix_expr) => prefix_expr.fmt(f),
+            Expression::InfixExpression(infix_expr) => infix_expr.fmt(f),
+            Expression::CallExpression(call_expr) => call_expr.fmt(f),
+            Expression::IfExpression(if_expr) => if_expr.fmt(f),
+            Expression::IndexExpression(index_expr) => index_expr.fmt(f),
+            Expression::AssignExpression(assign_expr) => assign_expr.fmt(f),
+            Expression::Identifier(ident) => ident.fmt(f),
+            Expression::IntegerLiteral(int_lit) => int_lit.fmt(f),
+            Expression::FloatLiteral(float_lit) => float_lit.fmt(f),
+            Expression::BooleanLiteral(bool_lit) => bool_lit.fmt(f),
+            Expression::StringLiteral(string_lit) => string_lit.fmt(f),
+            Expression::StringInterpolation(interpolation) => interpolation.fmt(f),
+            Expression::ArrayLiteral(array_lit) => array_lit.fmt(f),
+            Expression::MapLiteral(map_lit) => map_lit.fmt(f),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PrefixExpression {
+    pub id: NodeId,
+    pub span: Span,
+    pub operator: UnaryOp,
+    pub right_node: Box<Expression>,
+}
+
+impl PrefixExpression {
+    pub fn new(id: NodeId, span: Span, operator: UnaryOp, right_node: Expression) -> Self {
+        PrefixExpression {
+            id,
+            span,
+            operator,
+            right_node: Box::new(right_node),
+        }
+    }
+}
+
+impl fmt::Display for PrefixExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({}{})", self.operator, self.right_node)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InfixExpression {
+    pub id: NodeId,
+    pub span: Span,
+    pub left_node: Box<Expression>,
+    pub operator: BinaryOp,
+    pub right_node: Box<Expression>,
+}
+
+impl InfixExpression {
+    pub fn new(
+        id: NodeId,
+        span: Span,
+        left_node: Expression,
+        operator: BinaryOp,
+        right_node: Expression,
+    ) -> Self {
+        InfixExpression {
+            id,
+            span,
+            left_node: Box::new(left_node),
+            operator,
+            right_node: Box::new(right_node),
+        }
+    }
+}
+
+impl fmt::Display for InfixExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "({} {} {})",
+            self.left_node, self.operator, self.right_node
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CallExpression {
+    pub id: NodeId,
+    pub span: Span,
+    pub function: Box<Expression>, // The callee, usually an `Identifier`
+    pub arguments: Vec<Expression>,
+}
+
+impl CallExpression {
+    pub fn new(id: NodeId, span: Span, function: Expression, arguments: Vec<Expression>) -> Self {
+        CallExpression {
+            id,
+            span,
+            function: Box::new(function),
+            arguments,
+        }
+    }
+}
+
+impl fmt::Display for CallExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let arguments: Vec<String> = self.arguments.iter().map(|arg| arg.to_string()).collect();
+        write!(f, "{}({})", self.function, arguments.join(", "))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IndexExpression {
+    pub id: NodeId,
+    pub span: Span,
+    pub left_node: Box<Expression>, // The value being indexed
+    pub index: Box<Expression>,
+}
+
+impl IndexExpression {
+    pub fn new(id: NodeId, span: Span, left_node: Expression, index: Expression) -> Self {
+        IndexExpression {
+            id,
+            span,
+            left_node: Box::new(left_node),
+            index: Box::new(index),
+        }
+    }
+}
+
+impl fmt::Display for IndexExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({}[{}])", self.left_node, self.index)
+    }
+}
+
+/// What an `AssignExpression` can store into.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum AssignTarget {
+    Identifier(Identifier),           // x = ...
+    IndexExpression(IndexExpression), // a[i] = ... or m[key] = ...
+}
+
+impl AssignTarget {
+    pub fn span(&self) -> Span {
         match self {
-            Statement::ExpressionStatement(_) => NodeType::ExpressionStatement,
+            AssignTarget::Identifier(ident) => ident.span,
+            AssignTarget::IndexExpression(index_expr) => index_expr.span,
         }
     }
+}
 
-    pub fn json(&self) -> HashMap<String, serde_json::Value> {
+impl fmt::Display for AssignTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Statement::ExpressionStatement(expr_stmt) => expr_stmt.json(),
+            AssignTarget::Identifier(ident) => ident.fmt(f),
+            AssignTarget::IndexExpression(index_expr) => index_expr.fmt(f),
         }
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
-pub struct FloatLiteral {
-    pub value: f64,
+/// `target = value` or a compound assignment like `target += value`, which evaluates to
+/// the assigned value.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AssignExpression {
+    pub id: NodeId,
+    pub span: Span,
+    pub target: AssignTarget,
+    pub operator: Option<AssignOp>, // The compound operator, or `None` for a plain `=`
+    pub value: Box<Expression>,
 }
 
-impl FloatLiteral {
-    pub fn new(value: f64) -> Self {
-        FloatLiteral { value }
-    }
-
-    pub fn json(&self) -> HashMap<String, serde_json::Value> {
-        let mut map = HashMap::new();
-        map.insert(
-            "type".to_string(),
-            serde_json::Value::String(NodeType::FloatLiteral.value().to_string()),
-        );
-        map.insert(
-            "value".to_string(),
-            serde_json::Value::Number(serde_json::Number::from_f64(self.value).unwrap()),
-        );
-        map
+impl AssignExpression {
+    pub fn new(
+        id: NodeId,
+        span: Span,
+        target: AssignTarget,
+        operator: Option<AssignOp>,
+        value: Expression,
+    ) -> Self {
+        AssignExpression {
+            id,
+            span,
+            target,
+            operator,
+            value: Box::new(value),
+        }
+    }
+
+    /// `=`, or the compound operator like `+=`.
+    pub fn operator_str(&self) -> &'static str {
+        match self.operator {
+            Some(operator) => operator.as_str(),
+            None => "=",
+        }
+    }
+}
+
+impl fmt::Display for AssignExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "({} {} {})",
+            self.target,
+            self.operator_str(),
+            self.value
+        )
+    }
+}
+
+/// What follows the `else` of an `IfExpression`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(remote = "Self", tag = "type")]
+pub enum ElseBranch {
+    BlockStatement(BlockStatement),  // else { ... }
+    IfExpression(Box<IfExpression>), // else if ...
+}
+
+impl Serialize for ElseBranch {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        nested(|| ElseBranch::serialize(self, serializer))
+    }
+}
+
+impl<'de> Deserialize<'de> for ElseBranch {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        nested(|| ElseBranch::deserialize(deserializer))
+    }
+}
+
+impl fmt::Display for ElseBranch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ElseBranch::BlockStatement(block) => block.fmt(f),
+            ElseBranch::IfExpression(if_expr) => if_expr.fmt(f),
+        }
+    }
+}
+
+/// `if condition { ... } else { ... }`. It can be used as a statement or as an expression,
+/// in which case it evaluates to the value of the branch taken.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IfExpression {
+    pub id: NodeId,
+    pub span: Span,
+    pub condition: Box<Expression>,
+    pub consequence: BlockStatement,
+    pub alternative: Option<ElseBranch>,
+}
+
+impl IfExpression {
+    pub fn new(
+        id: NodeId,
+        span: Span,
+        condition: Expression,
+        consequence: BlockStatement,
+        alternative: Option<ElseBranch>,
+    ) -> Self {
+        IfExpression {
+            id,
+            span,
+            condition: Box::new(condition),
+            consequence,
+            alternative,
+        }
+    }
+}
+
+impl fmt::Display for IfExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "if {} {}", self.condition, self.consequence)?;
+        if let Some(ref alternative) = self.alternative {
+            write!(f, " else {}", alternative)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Identifier {
+    pub id: NodeId,
+    pub span: Span,
+    pub value: String,
+}
+
+impl Identifier {
+    pub fn new(id: NodeId, span: Span, value: String) -> Self {
+        Identifier { id, span, value }
+    }
+}
+
+impl fmt::Display for Identifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.value)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IntegerLiteral {
+    pub id: NodeId,
+    pub span: Span,
+    pub value: i64,
+}
+
+impl IntegerLiteral {
+    pub fn new(id: NodeId, span: Span, value: i64) -> Self {
+        IntegerLiteral { id, span, value }
+    }
+}
+
+impl fmt::Display for IntegerLiteral {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.value)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FloatLiteral {
+    pub id: NodeId,
+    pub span: Span,
+    #[serde(with = "float_value")]
+    pub value: f64,
+}
+
+impl FloatLiteral {
+    pub fn new(id: NodeId, span: Span, value: f64) -> Self {
+        FloatLiteral { id, span, value }
+    }
+}
+
+impl fmt::Display for FloatLiteral {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.value)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BooleanLiteral {
+    pub id: NodeId,
+    pub span: Span,
+    pub value: bool,
+}
+
+impl BooleanLiteral {
+    pub fn new(id: NodeId, span: Span, value: bool) -> Self {
+        BooleanLiteral { id, span, value }
+    }
+}
+
+impl fmt::Display for BooleanLiteral {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.value {
+            write!(f, "tremendous")
+        } else {
+            write!(f, "fake_news")
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StringLiteral {
+    pub id: NodeId,
+    pub span: Span,
+    pub value: String, // The decoded value, with escapes already applied
+}
+
+impl StringLiteral {
+    pub fn new(id: NodeId, span: Span, value: String) -> Self {
+        StringLiteral { id, span, value }
+    }
+}
+
+impl StringLiteral {
+    /// Writes the value with quotes, backslashes and braces escaped so it reads back
+    /// as the same string.
+    fn write_escaped(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for ch in self.value.chars() {
+            match ch {
+                '"' | '\\' | '{' | '}' => write!(f, "\\{}", ch)?,
+                '\n' => write!(f, "\\n")?,
+                '\t' => write!(f, "\\t")?,
+                '\r' => write!(f, "\\r")?,
+                '\0' => write!(f, "\\0")?,
+                _ => write!(f, "{}", ch)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for StringLiteral {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "\"")?;
+        self.write_escaped(f)?;
+        write!(f, "\"")
+    }
+}
+
+/// One piece of a `StringInterpolation`. Its JSON wraps the node in an object keyed by the
+/// variant name, since an interpolated expression has a `type` of its own.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum InterpolationPart {
+    StringLiteral(StringLiteral), // Text between interpolations, spanning its source text
+    Expression(Expression),       // The expression inside a `{...}`
+}
+
+/// A string literal containing `{expr}` interpolations. `parts` holds the text segments
+/// and the interpolated expressions in source order.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StringInterpolation {
+    pub id: NodeId,
+    pub span: Span,
+    pub parts: Vec<InterpolationPart>,
+}
+
+impl StringInterpolation {
+    pub fn new(id: NodeId, span: Span, parts: Vec<InterpolationPart>) -> Self {
+        StringInterpolation { id, span, parts }
+    }
+}
+
+impl fmt::Display for StringInterpolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "\"")?;
+        for part in &self.parts {
+            match part {
+                InterpolationPart::StringLiteral(segment) => segment.write_escaped(f)?,
+                InterpolationPart::Expression(expr) => write!(f, "{{{}}}", expr)?,
+            }
+        }
+        write!(f, "\"")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArrayLiteral {
+    pub id: NodeId,
+    pub span: Span,
+    pub elements: Vec<Expression>,
+}
+
+impl ArrayLiteral {
+    pub fn new(id: NodeId, span: Span, elements: Vec<Expression>) -> Self {
+        ArrayLiteral { id, span, elements }
+    }
+}
+
+impl fmt::Display for ArrayLiteral {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let elements: Vec<String> = self.elements.iter().map(|e| e.to_string()).collect();
+        write!(f, "[{}]", elements.join(", "))
+    }
+}
+
+/// `{ key: value, ... }`, with the pairs in source order.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MapLiteral {
+    pub id: NodeId,
+    pub span: Span,
+    pub pairs: Vec<(Expression, Expression)>,
+}
+
+impl MapLiteral {
+    pub fn new(id: NodeId, span: Span, pairs: Vec<(Expression, Expression)>) -> Self {
+        MapLiteral { id, span, pairs }
+    }
+}
+
+impl fmt::Display for MapLiteral {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pairs: Vec<String> = self
+            .pairs
+            .iter()
+            .map(|(key, value)| format!("{}: {}", key, value))
+            .collect();
+        write!(f, "{{{}}}", pairs.join(", "))
+    }
+}
+
+/// Walks a program without changing it. Every method visits the children of its node
+/// through the matching `walk_*` function by default, so a pass only overrides the nodes it
+/// cares about, and calls `walk_*` from the override if it still wants to reach the
+/// children. `visit_identifier` sees every identifier, including declared names and labels.
+pub trait Visitor {
+    fn visit_program(&mut self, program: &Program) {
+        walk_program(self, program);
+    }
+
+    fn visit_statement(&mut self, stmt: &Statement) {
+        walk_statement(self, stmt);
+    }
+
+    fn visit_expression_statement(&mut self, expr_stmt: &ExpressionStatement) {
+        walk_expression_statement(self, expr_stmt);
+    }
+
+    fn visit_let_statement(&mut self, let_stmt: &LetStatement) {
+        walk_let_statement(self, let_stmt);
+    }
+
+    fn visit_block_statement(&mut self, block: &BlockStatement) {
+        walk_block_statement(self, block);
+    }
+
+    fn visit_function_statement(&mut self, function: &FunctionStatement) {
+        walk_function_statement(self, function);
+    }
+
+    fn visit_return_statement(&mut self, return_stmt: &ReturnStatement) {
+        walk_return_statement(self, return_stmt);
+    }
+
+    fn visit_while_statement(&mut self, while_stmt: &WhileStatement) {
+        walk_while_statement(self, while_stmt);
+    }
+
+    fn visit_for_statement(&mut self, for_stmt: &ForStatement) {
+        walk_for_statement(self, for_stmt);
+    }
+
+    fn visit_break_statement(&mut self, break_stmt: &BreakStatement) {
+        walk_break_statement(self, break_stmt);
+    }
+
+    fn visit_continue_statement(&mut self, continue_stmt: &ContinueStatement) {
+        walk_continue_statement(self, continue_stmt);
+    }
+
+    fn visit_expression(&mut self, expr: &Expression) {
+        walk_expression(self, expr);
+    }
+
+    fn visit_prefix_expression(&mut self, prefix_expr: &PrefixExpression) {
+        walk_prefix_expression(self, prefix_expr);
+    }
+
+    fn visit_infix_expression(&mut self, infix_expr: &InfixExpression) {
+        walk_infix_expression(self, infix_expr);
+    }
+
+    fn visit_call_expression(&mut self, call_expr: &CallExpression) {
+        walk_call_expression(self, call_expr);
+    }
+
+    fn visit_index_expression(&mut self, index_expr: &IndexExpression) {
+        walk_index_expression(self, index_expr);
+    }
+
+    fn visit_assign_expression(&mut self, assign_expr: &AssignExpression) {
+        walk_assign_expression(self, assign_expr);
+    }
+
+    fn visit_assign_target(&mut self, target: &AssignTarget) {
+        walk_assign_target(self, target);
+    }
+
+    fn visit_if_expression(&mut self, if_expr: &IfExpression) {
+        walk_if_expression(self, if_expr);
+    }
+
+    fn visit_else_branch(&mut self, branch: &ElseBranch) {
+        walk_else_branch(self, branch);
+    }
+
+    fn visit_identifier(&mut self, _ident: &Identifier) {}
+
+    fn visit_integer_literal(&mut self, _int_lit: &IntegerLiteral) {}
+
+    fn visit_float_literal(&mut self, _float_lit: &FloatLiteral) {}
+
+    fn visit_boolean_literal(&mut self, _bool_lit: &BooleanLiteral) {}
+
+    fn visit_string_literal(&mut self, _string_lit: &StringLiteral) {}
+
+    fn visit_string_interpolation(&mut self, interpolation: &StringInterpolation) {
+        walk_string_interpolation(self, interpolation);
+    }
+
+    fn visit_interpolation_part(&mut self, part: &InterpolationPart) {
+        walk_interpolation_part(self, part);
+    }
+
+    fn visit_array_literal(&mut self, array_lit: &ArrayLiteral) {
+        walk_array_literal(self, array_lit);
+    }
+
+    fn visit_map_literal(&mut self, map_lit: &MapLiteral) {
+        walk_map_literal(self, map_lit);
+    }
+}
+
+pub fn walk_program<V: Visitor + ?Sized>(visitor: &mut V, program: &Program) {
+    for stmt in &program.statements {
+        visitor.visit_statement(stmt);
+    }
+}
+
+pub fn walk_statement<V: Visitor + ?Sized>(visitor: &mut V, stmt: &Statement) {
+    match stmt {
+        Statement::ExpressionStatement(expr_stmt) => visitor.visit_expression_statement(expr_stmt),
+        Statement::LetStatement(let_stmt) => visitor.visit_let_statement(let_stmt),
+        Statement::BlockStatement(block) => visitor.visit_block_statement(block),
+        Statement::FunctionStatement(function) => visitor.visit_function_statement(function),
+        Statement::ReturnStatement(return_stmt) => visitor.visit_return_statement(return_stmt),
+        Statement::WhileStatement(while_stmt) => visitor.visit_while_statement(while_stmt),
+        Statement::ForStatement(for_stmt) => visitor.visit_for_statement(for_stmt),
+        Statement::BreakStatement(break_stmt) => visitor.visit_break_statement(break_stmt),
+        Statement::ContinueStatement(continue_stmt) => {
+            visitor.visit_continue_statement(continue_stmt)
+        }
+    }
+}
+
+pub fn walk_expression_statement<V: Visitor + ?Sized>(
+    visitor: &mut V,
+    expr_stmt: &ExpressionStatement,
+) {
+    visitor.visit_expression(&expr_stmt.expr);
+}
+
+pub fn walk_let_statement<V: Visitor + ?Sized>(visitor: &mut V, let_stmt: &LetStatement) {
+    visitor.visit_identifier(&let_stmt.name);
+    visitor.visit_expression(&let_stmt.value);
+}
+
+pub fn walk_block_statement<V: Visitor + ?Sized>(visitor: &mut V, block: &BlockStatement) {
+    for stmt in &block.statements {
+        visitor.visit_statement(stmt);
+    }
+}
+
+pub fn walk_function_statement<V: Visitor + ?Sized>(visitor: &mut V, function: &FunctionStatement) {
+    visitor.visit_identifier(&function.name);
+    for param in &function.parameters {
+        visitor.visit_identifier(param);
+    }
+    visitor.visit_block_statement(&function.body);
+}
+
+pub fn walk_return_statement<V: Visitor + ?Sized>(visitor: &mut V, return_stmt: &ReturnStatement) {
+    if let Some(value) = &return_stmt.value {
+        visitor.visit_expression(value);
+    }
+}
+
+pub fn walk_while_statement<V: Visitor + ?Sized>(visitor: &mut V, while_stmt: &WhileStatement) {
+    if let Some(label) = &while_stmt.label {
+        visitor.visit_identifier(label);
+    }
+    visitor.visit_expression(&while_stmt.condition);
+    visitor.visit_block_statement(&while_stmt.body);
+}
+
+pub fn walk_for_statement<V: Visitor + ?Sized>(visitor: &mut V, for_stmt: &ForStatement) {
+    if let Some(label) = &for_stmt.label {
+        visitor.visit_identifier(label);
+    }
+    visitor.visit_identifier(&for_stmt.variable);
+    visitor.visit_expression(&for_stmt.start);
+    visitor.visit_expression(&for_stmt.end);
+    visitor.visit_block_statement(&for_stmt.body);
+}
+
+pub fn walk_break_statement<V: Visitor + ?Sized>(visitor: &mut V, break_stmt: &BreakStatement) {
+    if let Some(label) = &break_stmt.label {
+        visitor.visit_identifier(label);
+    }
+}
+
+pub fn walk_continue_statement<V: Visitor + ?Sized>(
+    visitor: &mut V,
+    continue_stmt: &ContinueStatement,
+) {
+    if let Some(label) = &continue_stmt.label {
+        visitor.visit_identifier(label);
+    }
+}
+
+pub fn walk_expression<V: Visitor + ?Sized>(visitor: &mut V, expr: &Expression) {
+    match expr {
+        Expression::PrefixExpression(prefix_expr) => visitor.visit_prefix_expression(prefix_expr),
+        Expression::InfixExpression(infix_expr) => visitor.visit_infix_expression(infix_expr),
+        Expression::CallExpression(call_expr) => visitor.visit_call_expression(call_expr),
+        Expression::IfExpression(if_expr) => visitor.visit_if_expression(if_expr),
+        Expression::Identifier(ident) => visitor.visit_identifier(ident),
+        Expression::IntegerLiteral(int_lit) => visitor.visit_integer_literal(int_lit),
+        Expression::FloatLiteral(float_lit) => visitor.visit_float_literal(float_lit),
+        Expression::BooleanLiteral(bool_lit) => visitor.visit_boolean_literal(bool_lit),
+        Expression::StringLiteral(string_lit) => visitor.visit_string_literal(string_lit),
+        Expression::StringInterpolation(interpolation) => {
+            visitor.visit_string_interpolation(interpolation)
+        }
+        Expression::ArrayLiteral(array_lit) => visitor.visit_array_literal(array_lit),
+        Expression::MapLiteral(map_lit) => visitor.visit_map_literal(map_lit),
+        Expression::IndexExpression(index_expr) => visitor.visit_index_expression(index_expr),
+        Expression::AssignExpression(assign_expr) => visitor.visit_assign_expression(assign_expr),
+    }
+}
+
+pub fn walk_prefix_expression<V: Visitor + ?Sized>(
+    visitor: &mut V,
+    prefix_expr: &PrefixExpression,
+) {
+    visitor.visit_expression(&prefix_expr.right_node);
+}
+
+pub fn walk_infix_expression<V: Visitor + ?Sized>(visitor: &mut V, infix_expr: &InfixExpression) {
+    visitor.visit_expression(&infix_expr.left_node);
+    visitor.visit_expression(&infix_expr.right_node);
+}
+
+pub fn walk_call_expression<V: Visitor + ?Sized>(visitor: &mut V, call_expr: &CallExpression) {
+    visitor.visit_expression(&call_expr.function);
+    for arg in &call_expr.arguments {
+        visitor.visit_expression(arg);
+    }
+}
+
+pub fn walk_index_expression<V: Visitor + ?Sized>(visitor: &mut V, index_expr: &IndexExpression) {
+    visitor.visit_expression(&index_expr.left_node);
+    visitor.visit_expression(&index_expr.index);
+}
+
+pub fn walk_assign_expression<V: Visitor + ?Sized>(
+    visitor: &mut V,
+    assign_expr: &AssignExpression,
+) {
+    visitor.visit_assign_target(&assign_expr.target);
+    visitor.visit_expression(&assign_expr.value);
+}
+
+pub fn walk_assign_target<V: Visitor + ?Sized>(visitor: &mut V, target: &AssignTarget) {
+    match target {
+        AssignTarget::Identifier(ident) => visitor.visit_identifier(ident),
+        AssignTarget::IndexExpression(index_expr) => visitor.visit_index_expression(index_expr),
+    }
+}
+
+pub fn walk_if_expression<V: Visitor + ?Sized>(visitor: &mut V, if_expr: &IfExpression) {
+    visitor.visit_expression(&if_expr.condition);
+    visitor.visit_block_statement(&if_expr.consequence);
+    if let Some(alternative) = &if_expr.alternative {
+        visitor.visit_else_branch(alternative);
+    }
+}
+
+pub fn walk_else_branch<V: Visitor + ?Sized>(visitor: &mut V, branch: &ElseBranch) {
+    match branch {
+        ElseBranch::BlockStatement(block) => visitor.visit_block_statement(block),
+        ElseBranch::IfExpression(if_expr) => visitor.visit_if_expression(if_expr),
+    }
+}
+
+pub fn walk_string_interpolation<V: Visitor + ?Sized>(
+    visitor: &mut V,
+    interpolation: &StringInterpolation,
+) {
+    for part in &interpolation.parts {
+        visitor.visit_interpolation_part(part);
+    }
+}
+
+pub fn walk_interpolation_part<V: Visitor + ?Sized>(visitor: &mut V, part: &InterpolationPart) {
+    match part {
+        InterpolationPart::StringLiteral(literal) => visitor.visit_string_literal(literal),
+        InterpolationPart::Expression(expr) => visitor.visit_expression(expr),
+    }
+}
+
+pub fn walk_array_literal<V: Visitor + ?Sized>(visitor: &mut V, array_lit: &ArrayLiteral) {
+    for element in &array_lit.elements {
+        visitor.visit_expression(element);
+    }
+}
+
+pub fn walk_map_literal<V: Visitor + ?Sized>(visitor: &mut V, map_lit: &MapLiteral) {
+    for (key, value) in &map_lit.pairs {
+        visitor.visit_expression(key);
+        visitor.visit_expression(value);
+    }
+}
+
+/// Like `Visitor`, but with mutable access to every node, for passes that rewrite the
+/// program in place. The `walk_*_mut` functions visit the children of each node.
+pub trait VisitorMut {
+    fn visit_program(&mut self, program: &mut Program) {
+        walk_program_mut(self, program);
+    }
+
+    fn visit_statement(&mut self, stmt: &mut Statement) {
+        walk_statement_mut(self, stmt);
+    }
+
+    fn visit_expression_statement(&mut self, expr_stmt: &mut ExpressionStatement) {
+        walk_expression_statement_mut(self, expr_stmt);
+    }
+
+    fn visit_let_statement(&mut self, let_stmt: &mut LetStatement) {
+        walk_let_statement_mut(self, let_stmt);
+    }
+
+    fn visit_block_statement(&mut self, block: &mut BlockStatement) {
+        walk_block_statement_mut(self, block);
+    }
+
+    fn visit_function_statement(&mut self, function: &mut FunctionStatement) {
+        walk_function_statement_mut(self, function);
+    }
+
+    fn visit_return_statement(&mut self, return_stmt: &mut ReturnStatement) {
+        walk_return_statement_mut(self, return_stmt);
+    }
+
+    fn visit_while_statement(&mut self, while_stmt: &mut WhileStatement) {
+        walk_while_statement_mut(self, while_stmt);
+    }
+
+    fn visit_for_statement(&mut self, for_stmt: &mut ForStatement) {
+        walk_for_statement_mut(self, for_stmt);
+    }
+
+    fn visit_break_statement(&mut self, break_stmt: &mut BreakStatement) {
+        walk_break_statement_mut(self, break_stmt);
+    }
+
+    fn visit_continue_statement(&mut self, continue_stmt: &mut ContinueStatement) {
+        walk_continue_statement_mut(self, continue_stmt);
+    }
+
+    fn visit_expression(&mut self, expr: &mut Expression) {
+        walk_expression_mut(self, expr);
+    }
+
+    fn visit_prefix_expression(&mut self, prefix_expr: &mut PrefixExpression) {
+        walk_prefix_expression_mut(self, prefix_expr);
+    }
+
+    fn visit_infix_expression(&mut self, infix_expr: &mut InfixExpression) {
+        walk_infix_expression_mut(self, infix_expr);
+    }
+
+    fn visit_call_expression(&mut self, call_expr: &mut CallExpression) {
+        walk_call_expression_mut(self, call_expr);
+    }
+
+    fn visit_index_expression(&mut self, index_expr: &mut IndexExpression) {
+        walk_index_expression_mut(self, index_expr);
+    }
+
+    fn visit_assign_expression(&mut self, assign_expr: &mut AssignExpression) {
+        walk_assign_expression_mut(self, assign_expr);
+    }
+
+    fn visit_assign_target(&mut self, target: &mut AssignTarget) {
+        walk_assign_target_mut(self, target);
+    }
+
+    fn visit_if_expression(&mut self, if_expr: &mut IfExpression) {
+        walk_if_expression_mut(self, if_expr);
+    }
+
+    fn visit_else_branch(&mut self, branch: &mut ElseBranch) {
+        walk_else_branch_mut(self, branch);
+    }
+
+    fn visit_identifier(&mut self, _ident: &mut Identifier) {}
+
+    fn visit_integer_literal(&mut self, _int_lit: &mut IntegerLiteral) {}
+
+    fn visit_float_literal(&mut self, _float_lit: &mut FloatLiteral) {}
+
+    fn visit_boolean_literal(&mut self, _bool_lit: &mut BooleanLiteral) {}
+
+    fn visit_string_literal(&mut self, _string_lit: &mut StringLiteral) {}
+
+    fn visit_string_interpolation(&mut self, interpolation: &mut StringInterpolation) {
+        walk_string_interpolation_mut(self, interpolation);
+    }
+
+    fn visit_interpolation_part(&mut self, part: &mut InterpolationPart) {
+        walk_interpolation_part_mut(self, part);
+    }
+
+    fn visit_array_literal(&mut self, array_lit: &mut ArrayLiteral) {
+        walk_array_literal_mut(self, array_lit);
+    }
+
+    fn visit_map_literal(&mut self, map_lit: &mut MapLiteral) {
+        walk_map_literal_mut(self, map_lit);
+    }
+}
+
+pub fn walk_program_mut<V: VisitorMut + ?Sized>(visitor: &mut V, program: &mut Program) {
+    for stmt in &mut program.statements {
+        visitor.visit_statement(stmt);
+    }
+}
+
+pub fn walk_statement_mut<V: VisitorMut + ?Sized>(visitor: &mut V, stmt: &mut Statement) {
+    match stmt {
+        Statement::ExpressionStatement(expr_stmt) => visitor.visit_expression_statement(expr_stmt),
+        Statement::LetStatement(let_stmt) => visitor.visit_let_statement(let_stmt),
+        Statement::BlockStatement(block) => visitor.visit_block_statement(block),
+        Statement::FunctionStatement(function) => visitor.visit_function_statement(function),
+        Statement::ReturnStatement(return_stmt) => visitor.visit_return_statement(return_stmt),
+        Statement::WhileStatement(while_stmt) => visitor.visit_while_statement(while_stmt),
+        Statement::ForStatement(for_stmt) => visitor.visit_for_statement(for_stmt),
+        Statement::BreakStatement(break_stmt) => visitor.visit_break_statement(break_stmt),
+        Statement::ContinueStatement(continue_stmt) => {
+            visitor.visit_continue_statement(continue_stmt)
+        }
+    }
+}
+
+pub fn walk_expression_statement_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    expr_stmt: &mut ExpressionStatement,
+) {
+    visitor.visit_expression(&mut expr_stmt.expr);
+}
+
+pub fn walk_let_statement_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    let_stmt: &mut LetStatement,
+) {
+    visitor.visit_identifier(&mut let_stmt.name);
+    visitor.visit_expression(&mut let_stmt.value);
+}
+
+pub fn walk_block_statement_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    block: &mut BlockStatement,
+) {
+    for stmt in &mut block.statements {
+        visitor.visit_statement(stmt);
+    }
+}
+
+pub fn walk_function_statement_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    function: &mut FunctionStatement,
+) {
+    visitor.visit_identifier(&mut function.name);
+    for param in &mut function.parameters {
+        visitor.visit_identifier(param);
+    }
+    visitor.visit_block_statement(&mut function.body);
+}
+
+pub fn walk_return_statement_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    return_stmt: &mut ReturnStatement,
+) {
+    if let Some(value) = &mut return_stmt.value {
+        visitor.visit_expression(value);
+    }
+}
+
+pub fn walk_while_statement_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    while_stmt: &mut WhileStatement,
+) {
+    if let Some(label) = &mut while_stmt.label {
+        visitor.visit_identifier(label);
+    }
+    visitor.visit_expression(&mut while_stmt.condition);
+    visitor.visit_block_statement(&mut while_stmt.body);
+}
+
+pub fn walk_for_statement_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    for_stmt: &mut ForStatement,
+) {
+    if let Some(label) = &mut for_stmt.label {
+        visitor.visit_identifier(label);
+    }
+    visitor.visit_identifier(&mut for_stmt.variable);
+    visitor.visit_expression(&mut for_stmt.start);
+    visitor.visit_expression(&mut for_stmt.end);
+    visitor.visit_block_statement(&mut for_stmt.body);
+}
+
+pub fn walk_break_statement_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    break_stmt: &mut BreakStatement,
+) {
+    if let Some(label) = &mut break_stmt.label {
+        visitor.visit_identifier(label);
+    }
+}
+
+pub fn walk_continue_statement_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    continue_stmt: &mut ContinueStatement,
+) {
+    if let Some(label) = &mut continue_stmt.label {
+        visitor.visit_identifier(label);
+    }
+}
+
+pub fn walk_expression_mut<V: VisitorMut + ?Sized>(visitor: &mut V, expr: &mut Expression) {
+    match expr {
+        Expression::PrefixExpression(prefix_expr) => visitor.visit_prefix_expression(prefix_expr),
+        Expression::InfixExpression(infix_expr) => visitor.visit_infix_expression(infix_expr),
+        Expression::CallExpression(call_expr) => visitor.visit_call_expression(call_expr),
+        Expression::IfExpression(if_expr) => visitor.visit_if_expression(if_expr),
+        Expression::Identifier(ident) => visitor.visit_identifier(ident),
+        Expression::IntegerLiteral(int_lit) => visitor.visit_integer_literal(int_lit),
+        Expression::FloatLiteral(float_lit) => visitor.visit_float_literal(float_lit),
+        Expression::BooleanLiteral(bool_lit) => visitor.visit_boolean_literal(bool_lit),
+        Expression::StringLiteral(string_lit) => visitor.visit_string_literal(string_lit),
+        Expression::StringInterpolation(interpolation) => {
+            visitor.visit_string_interpolation(interpolation)
+        }
+        Expression::ArrayLiteral(array_lit) => visitor.visit_array_literal(array_lit),
+        Expression::MapLiteral(map_lit) => visitor.visit_map_literal(map_lit),
+        Expression::IndexExpression(index_expr) => visitor.visit_index_expression(index_expr),
+        Expression::AssignExpression(assign_expr) => visitor.visit_assign_expression(assign_expr),
+    }
+}
+
+pub fn walk_prefix_expression_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    prefix_expr: &mut PrefixExpression,
+) {
+    visitor.visit_expression(&mut prefix_expr.right_node);
+}
+
+pub fn walk_infix_expression_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    infix_expr: &mut InfixExpression,
+) {
+    visitor.visit_expression(&mut infix_expr.left_node);
+    visitor.visit_expression(&mut infix_expr.right_node);
+}
+
+pub fn walk_call_expression_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    call_expr: &mut CallExpression,
+) {
+    visitor.visit_expression(&mut call_expr.function);
+    for arg in &mut call_expr.arguments {
+        visitor.visit_expression(arg);
+    }
+}
+
+pub fn walk_index_expression_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    index_expr: &mut IndexExpression,
+) {
+    visitor.visit_expression(&mut index_expr.left_node);
+    visitor.visit_expression(&mut index_expr.index);
+}
+
+pub fn walk_assign_expression_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    assign_expr: &mut AssignExpression,
+) {
+    visitor.visit_assign_target(&mut assign_expr.target);
+    visitor.visit_expression(&mut assign_expr.value);
+}
+
+pub fn walk_assign_target_mut<V: VisitorMut + ?Sized>(visitor: &mut V, target: &mut AssignTarget) {
+    match target {
+        AssignTarget::Identifier(ident) => visitor.visit_identifier(ident),
+        AssignTarget::IndexExpression(index_expr) => visitor.visit_index_expression(index_expr),
+    }
+}
+
+pub fn walk_if_expression_mut<V: VisitorMut + ?Sized>(visitor: &mut V, if_expr: &mut IfExpression) {
+    visitor.visit_expression(&mut if_expr.condition);
+    visitor.visit_block_statement(&mut if_expr.consequence);
+    if let Some(alternative) = &mut if_expr.alternative {
+        visitor.visit_else_branch(alternative);
+    }
+}
+
+pub fn walk_else_branch_mut<V: VisitorMut + ?Sized>(visitor: &mut V, branch: &mut ElseBranch) {
+    match branch {
+        ElseBranch::BlockStatement(block) => visitor.visit_block_statement(block),
+        ElseBranch::IfExpression(if_expr) => visitor.visit_if_expression(if_expr),
+    }
+}
+
+pub fn walk_string_interpolation_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    interpolation: &mut StringInterpolation,
+) {
+    for part in &mut interpolation.parts {
+        visitor.visit_interpolation_part(part);
+    }
+}
+
+pub fn walk_interpolation_part_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    part: &mut InterpolationPart,
+) {
+    match part {
+        InterpolationPart::StringLiteral(literal) => visitor.visit_string_literal(literal),
+        InterpolationPart::Expression(expr) => visitor.visit_expression(expr),
+    }
+}
+
+pub fn walk_array_literal_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    array_lit: &mut ArrayLiteral,
+) {
+    for element in &mut array_lit.elements {
+        visitor.visit_expression(element);
+    }
+}
+
+pub fn walk_map_literal_mut<V: VisitorMut + ?Sized>(visitor: &mut V, map_lit: &mut MapLiteral) {
+    for (key, value) in &mut map_lit.pairs {
+        visitor.visit_expression(key);
+        visitor.visit_expression(value);
+    }
+}
+
+/// Writes floats as JSON numbers, except for NaN and the infinities, which JSON has no
+/// numbers for. Those are written as the strings `"NaN"`, `"inf"` and `"-inf"`.
+mod float_value {
+    use serde::de::{Error, Unexpected};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum FloatValue {
+        Number(f64),
+        Name(String),
+    }
+
+    pub fn serialize<S: Serializer>(value: &f64, serializer: S) -> Result<S::Ok, S::Error> {
+        if value.is_nan() {
+            serializer.serialize_str("NaN")
+        } else if value.is_infinite() {
+            serializer.serialize_str(if *value > 0.0 { "inf" } else { "-inf" })
+        } else {
+            serializer.serialize_f64(*value)
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+        match FloatValue::deserialize(deserializer)? {
+            FloatValue::Number(value) => Ok(value),
+            FloatValue::Name(name) => match name.as_str() {
+                "NaN" => Ok(f64::NAN),
+                "inf" => Ok(f64::INFINITY),
+                "-inf" => Ok(f64::NEG_INFINITY),
+                _ => Err(D::Error::invalid_value(
+                    Unexpected::Str(&name),
+                    &"a number, \"NaN\", \"inf\" or \"-inf\"",
+                )),
+            },
+        }
     }
 }
@@ -0,0 +1,89 @@
+// i have the best functions
+// everybody agrees, even the fake news
+
+use crate::interpreter::{HashKey, RuntimeError, Value};
+
+/// Functions every trump program can call without declaring them. A `deal` or variable
+/// with the same name shadows the built-in one.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Builtin {
+    Len,    // len(x): the number of elements in an array or map, or characters in a string
+    Keys,   // keys(map): an array of the map's keys in sorted order
+    Insert, // insert(map, key, value): sets `key` and returns the value it replaced, or null
+    Remove, // remove(map, key): removes `key` and returns its value, or null
+}
+
+impl Builtin {
+    pub fn lookup(name: &str) -> Option<Builtin> {
+        match name {
+            "len" => Some(Builtin::Len),
+            "keys" => Some(Builtin::Keys),
+            "insert" => Some(Builtin::Insert),
+            "remove" => Some(Builtin::Remove),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Builtin::Len => "len",
+            Builtin::Keys => "keys",
+            Builtin::Insert => "insert",
+            Builtin::Remove => "remove",
+        }
+    }
+
+    pub fn arity(&self) -> usize {
+        match self {
+            Builtin::Len | Builtin::Keys => 1,
+            Builtin::Remove => 2,
+            Builtin::Insert => 3,
+        }
+    }
+
+    /// Calls the built-in with arguments that have already been checked against `arity()`.
+    pub fn call(&self, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        match (self, arguments.as_slice()) {
+            (Builtin::Len, [Value::Array(elements)]) => Self::_length(elements.borrow().len()),
+            (Builtin::Len, [Value::Map(entries)]) => Self::_length(entries.borrow().len()),
+            (Builtin::Len, [Value::String(value)]) => Self::_length(value.chars().count()),
+            (Builtin::Len, [other]) => Err(RuntimeError::TypeMismatch {
+                expected: "Array, Map or String",
+                found: other.type_name(),
+            }),
+            (Builtin::Keys, [Value::Map(entries)]) => {
+                let mut keys: Vec<HashKey> = entries.borrow().keys().cloned().collect();
+                keys.sort();
+                Ok(Value::new_array(
+                    keys.into_iter().map(Value::from).collect(),
+                ))
+            }
+            (Builtin::Insert, [Value::Map(entries), key, value]) => {
+                let key = HashKey::try_from(key)?;
+                let previous = entries.borrow_mut().insert(key, value.clone());
+                Ok(previous.unwrap_or(Value::Null))
+            }
+            (Builtin::Remove, [Value::Map(entries), key]) => {
+                let key = HashKey::try_from(key)?;
+                let removed = entries.borrow_mut().remove(&key);
+                Ok(removed.unwrap_or(Value::Null))
+            }
+            (Builtin::Keys | Builtin::Insert | Builtin::Remove, [other, ..]) => {
+                Err(RuntimeError::TypeMismatch {
+                    expected: "Map",
+                    found: other.type_name(),
+                })
+            }
+            (_, _) => Err(RuntimeError::WrongArgumentCount {
+                name: self.name().to_string(),
+                expected: self.arity(),
+                found: arguments.len(),
+            }),
+        }
+    }
+
+    fn _length(length: usize) -> Result<Value, RuntimeError> {
+        // Nothing in memory can have more than i64::MAX elements
+        Ok(Value::Integer(length as i64))
+    }
+}
@@ -0,0 +1,156 @@
+// many people are saying this is the best checker
+// nobody has ever seen a checker like this
+
+use crate::ast::{
+    walk_function_statement, walk_return_statement, BlockStatement, BreakStatement,
+    ContinueStatement, ForStatement, FunctionStatement, Identifier, Program, ReturnStatement,
+    Visitor, WhileStatement,
+};
+use crate::token::Span;
+use std::fmt;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum CheckErrorKind {
+    BreakOutsideLoop,      // A `break` that isn't inside any loop of its own `deal`
+    ContinueOutsideLoop,   // A `continue` that isn't inside any loop of its own `deal`
+    UndefinedLabel,        // A `break` or `continue` naming a label no enclosing loop has
+    ReturnOutsideFunction, // A `return` that isn't inside a `deal`
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct CheckError {
+    pub kind: CheckErrorKind,
+    pub label: Option<String>, // The label the offending statement named, if any
+    pub span: Span,            // Where the offending statement is
+}
+
+impl CheckError {
+    pub fn new(kind: CheckErrorKind, label: Option<String>, span: Span) -> Self {
+        Self { kind, label, span }
+    }
+}
+
+impl fmt::Display for CheckError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            CheckErrorKind::BreakOutsideLoop => write!(f, "`break` outside of a loop"),
+            CheckErrorKind::ContinueOutsideLoop => write!(f, "`continue` outside of a loop"),
+            CheckErrorKind::UndefinedLabel => write!(
+                f,
+                "No enclosing loop is labelled '{}'",
+                self.label.as_deref().unwrap_or_default()
+            ),
+            CheckErrorKind::ReturnOutsideFunction => write!(f, "`return` outside of a deal"),
+        }
+    }
+}
+
+impl std::error::Error for CheckError {}
+
+/// Walks a parsed program looking for mistakes the grammar can't rule out, like a `break`
+/// with no loop around it.
+#[derive(Debug, Default)]
+pub struct Checker {
+    errors: Vec<CheckError>,
+    loop_labels: Vec<Option<String>>, // One entry per loop around the current node, innermost last
+    function_depth: usize,
+}
+
+impl Checker {
+    pub fn new() -> Self {
+        Checker {
+            errors: Vec::new(),
+            loop_labels: Vec::new(),
+            function_depth: 0,
+        }
+    }
+
+    /// Checks the whole program. Everything wrong with it is reported in `errors()`.
+    pub fn check_program(&mut self, program: &Program) {
+        self.visit_program(program);
+    }
+
+    pub fn errors(&self) -> &[CheckError] {
+        &self.errors
+    }
+
+    fn _check_loop_body(&mut self, label: &Option<Identifier>, body: &BlockStatement) {
+        self.loop_labels
+            .push(label.as_ref().map(|label| label.value.clone()));
+        self.visit_block_statement(body);
+        self.loop_labels.pop();
+    }
+
+    /// Checks that a `break` or `continue` has a loop to jump to, and that the loop with
+    /// the named label encloses it if it names one.
+    fn _check_jump(
+        &mut self,
+        outside_loop: CheckErrorKind,
+        label: &Option<Identifier>,
+        span: Span,
+    ) {
+        let label = label.as_ref().map(|label| label.value.clone());
+
+        if self.loop_labels.is_empty() {
+            self.errors.push(CheckError::new(outside_loop, label, span));
+        } else if label.is_some() && !self.loop_labels.contains(&label) {
+            self.errors
+                .push(CheckError::new(CheckErrorKind::UndefinedLabel, label, span));
+        }
+    }
+}
+
+/// Only loops, `deal`s and the statements that jump out of them matter to the checker.
+/// Everything else is just walked through.
+impl Visitor for Checker {
+    /// A `deal` body starts outside of every loop, since the loops around the declaration
+    /// aren't running when the function is called.
+    fn visit_function_statement(&mut self, function: &FunctionStatement) {
+        let outer_loop_labels = std::mem::take(&mut self.loop_labels);
+        self.function_depth += 1;
+
+        walk_function_statement(self, function);
+
+        self.function_depth -= 1;
+        self.loop_labels = outer_loop_labels;
+    }
+
+    fn visit_return_statement(&mut self, return_stmt: &ReturnStatement) {
+        if self.function_depth == 0 {
+            self.errors.push(CheckError::new(
+                CheckErrorKind::ReturnOutsideFunction,
+                None,
+                return_stmt.span,
+            ));
+        }
+
+        walk_return_statement(self, return_stmt);
+    }
+
+    fn visit_while_statement(&mut self, while_stmt: &WhileStatement) {
+        self.visit_expression(&while_stmt.condition);
+        self._check_loop_body(&while_stmt.label, &while_stmt.body);
+    }
+
+    fn visit_for_statement(&mut self, for_stmt: &ForStatement) {
+        self.visit_expression(&for_stmt.start);
+        self.visit_expression(&for_stmt.end);
+        self._check_loop_body(&for_stmt.label, &for_stmt.body);
+    }
+
+    fn visit_break_statement(&mut self, break_stmt: &BreakStatement) {
+        self._check_jump(
+            CheckErrorKind::BreakOutsideLoop,
+            &break_stmt.label,
+            break_stmt.span,
+        );
+    }
+
+    fn visit_continue_statement(&mut self, continue_stmt: &ContinueStatement) {
+        self._check_jump(
+            CheckErrorKind::ContinueOutsideLoop,
+            &continue_stmt.label,
+            continue_stmt.span,
+        );
+    }
+}
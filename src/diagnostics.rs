@@ -0,0 +1,215 @@
+// nobody reads errors better than me, believe me
+
+use crate::checker::{CheckError, CheckErrorKind};
+use crate::lexer::{LexError, LexErrorKind};
+use crate::parser::{ParseError, ParseErrorKind};
+use crate::token::{Span, TokenType};
+use std::fmt;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,       // The headline shown after `error:`
+    pub span: Span,            // The source range the carets point at
+    pub label: Option<String>, // Text printed right after the carets
+    pub notes: Vec<String>,
+    pub help: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, message: impl Into<String>, span: Span) -> Self {
+        Self {
+            severity,
+            message: message.into(),
+            span,
+            label: None,
+            notes: Vec::new(),
+            help: None,
+        }
+    }
+
+    pub fn error(message: impl Into<String>, span: Span) -> Self {
+        Self::new(Severity::Error, message, span)
+    }
+
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+
+    /// Renders the diagnostic the way rustc does, e.g.
+    ///
+    /// ```text
+    /// error: Expected next token to be RightParen, got Eof instead
+    ///  --> test.trump:1:7
+    ///   |
+    /// 1 | (1 + 2
+    ///   |       ^ expected RightParen
+    /// ```
+    pub fn render(&self, file_name: &str, source: &str) -> String {
+        let line_text = source
+            .lines()
+            .nth(self.span.line.saturating_sub(1))
+            .unwrap_or("");
+        let line_no = self.span.line.to_string();
+        let gutter = " ".repeat(line_no.len());
+
+        // Keep tabs in the padding so the carets line up with the source line
+        let padding: String = line_text
+            .chars()
+            .take(self.span.column)
+            .map(|ch| if ch == '\t' { '\t' } else { ' ' })
+            .collect();
+        let remaining = line_text.chars().count().saturating_sub(self.span.column);
+        let width = source
+            .get(self.span.start..self.span.end)
+            .map_or(0, |text| text.chars().count())
+            .min(remaining)
+            .max(1);
+
+        let mut out = format!("{}: {}\n", self.severity, self.message);
+        out += &format!(
+            "{}--> {}:{}:{}\n",
+            gutter,
+            file_name,
+            self.span.line,
+            self.span.column + 1
+        );
+        out += &format!("{} |\n", gutter);
+        out += &format!("{} | {}\n", line_no, line_text);
+        out += &format!("{} | {}{}", gutter, padding, "^".repeat(width));
+        if let Some(ref label) = self.label {
+            out += &format!(" {}", label);
+        }
+        out += "\n";
+
+        if !self.notes.is_empty() || self.help.is_some() {
+            out += &format!("{} |\n", gutter);
+        }
+        for note in &self.notes {
+            out += &format!("{} = note: {}\n", gutter, note);
+        }
+        if let Some(ref help) = self.help {
+            out += &format!("{} = help: {}\n", gutter, help);
+        }
+
+        out
+    }
+}
+
+impl From<&LexError> for Diagnostic {
+    fn from(error: &LexError) -> Self {
+        let diagnostic = Diagnostic::error(error.to_string(), error.span);
+
+        match error.kind {
+            LexErrorKind::UnexpectedCharacter => {
+                diagnostic.with_label("not valid in a trump program")
+            }
+            LexErrorKind::UnterminatedString => diagnostic.with_label("missing closing `\"`"),
+            LexErrorKind::UnclosedInterpolation => diagnostic
+                .with_label("this `{` is never closed")
+                .with_help(r"close it with `}`, or write `\{` for a literal brace"),
+            LexErrorKind::UnterminatedBlockComment => diagnostic.with_label("missing closing `*/`"),
+            LexErrorKind::MalformedNumber => diagnostic.with_label("not a valid number").with_help(
+                "numbers look like `42`, `1_000`, `0xff`, `0b1010`, `0o17`, `2.5` or `1e9`",
+            ),
+            LexErrorKind::InvalidNumberSuffix => diagnostic
+                .with_label("unknown suffix")
+                .with_help("the only number suffixes are `i64` and `f64`"),
+        }
+    }
+}
+
+impl From<&ParseError> for Diagnostic {
+    fn from(error: &ParseError) -> Self {
+        if let ParseErrorKind::Lex(kind) = error.kind {
+            return Diagnostic::from(&LexError::new(kind, error.lexeme.clone(), error.span));
+        }
+
+        let diagnostic = Diagnostic::error(error.to_string(), error.span);
+
+        match (error.kind, error.expected) {
+            (ParseErrorKind::UnexpectedToken, Some(TokenType::RightParen)) => diagnostic
+                .with_label("expected RightParen")
+                .with_help("close the group with `)`"),
+            (ParseErrorKind::UnexpectedToken, Some(expected)) => {
+                diagnostic.with_label(format!("expected {:?}", expected))
+            }
+            (ParseErrorKind::UnexpectedToken, None) => {
+                diagnostic.with_label("a statement cannot start here")
+            }
+            (ParseErrorKind::NoPrefixParseFn, _) => diagnostic
+                .with_label("expected an expression")
+                .with_note(format!("an expression cannot start with {:?}", error.found)),
+            (ParseErrorKind::InvalidLiteral, _) => {
+                diagnostic.with_label(format!("not a valid {:?}", error.found))
+            }
+            (ParseErrorKind::LiteralOverflow, _) if error.found == TokenType::Float => diagnostic
+                .with_label("too large for a 64-bit float")
+                .with_note("floats must be finite"),
+            (ParseErrorKind::LiteralOverflow, _) => diagnostic
+                .with_label("doesn't fit in 64 bits")
+                .with_note(format!(
+                    "integers must be between {} and {}",
+                    i64::MIN,
+                    i64::MAX
+                )),
+            (ParseErrorKind::InvalidAssignmentTarget, _) => diagnostic
+                .with_label("the left side can't be assigned to")
+                .with_help(
+                    "only variables and elements like `a[0]` or `m[key]` can be assigned to",
+                ),
+            (ParseErrorKind::NestingTooDeep, _) => diagnostic
+                .with_label("nested too deeply")
+                .with_help("pull the inner parts out into variables or deals"),
+            (ParseErrorKind::Lex(_), _) => diagnostic,
+            (ParseErrorKind::InvalidEscape, _) => diagnostic
+                .with_label("unknown escape sequence")
+                .with_help(r#"valid escapes are \n, \t, \r, \0, \\, \", \{, \} and \u{...}"#),
+        }
+    }
+}
+
+impl From<&CheckError> for Diagnostic {
+    fn from(error: &CheckError) -> Self {
+        let diagnostic = Diagnostic::error(error.to_string(), error.span);
+
+        match error.kind {
+            CheckErrorKind::BreakOutsideLoop => diagnostic
+                .with_label("there's no loop to break out of")
+                .with_help("`break` can only be used in a `while` or `for` body"),
+            CheckErrorKind::ContinueOutsideLoop => diagnostic
+                .with_label("there's no loop to continue")
+                .with_help("`continue` can only be used in a `while` or `for` body"),
+            CheckErrorKind::UndefinedLabel => diagnostic.with_label("unknown label"),
+            CheckErrorKind::ReturnOutsideFunction => diagnostic
+                .with_label("not inside a deal")
+                .with_help("`return` can only be used in a `deal` body"),
+        }
+    }
+}
@@ -0,0 +1,1068 @@
+// we're gonna win so much
+// you're gonna get tired of winning
+
+use crate::ast::{
+    ArrayLiteral, AssignExpression, AssignTarget, BinaryOp, BlockStatement, CallExpression,
+    ElseBranch, Expression, ForStatement, FunctionStatement, Identifier, IfExpression,
+    IndexExpression, InfixExpression, InterpolationPart, LetStatement, MapLiteral,
+    PrefixExpression, Program, ReturnStatement, Statement, StringInterpolation, UnaryOp,
+    WhileStatement,
+};
+use crate::builtins::Builtin;
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+/// How many calls deep a program may go before we call it a stack overflow. Each call
+/// takes a few kilobytes of native stack in debug builds, so embedders should run the
+/// interpreter on a thread with a generous stack, like `main` does.
+const MAX_CALL_DEPTH: usize = 1000;
+
+#[derive(Debug, Clone)]
+pub enum Value {
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+    String(String),
+    Function(Rc<Function>),
+    Builtin(Builtin),
+    Array(Rc<RefCell<Vec<Value>>>), // Shared, so assigning to an element is seen through every copy
+    Map(Rc<RefCell<HashMap<HashKey, Value>>>), // Shared like arrays
+    Null,
+}
+
+impl Value {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Integer(_) => "Integer",
+            Value::Float(_) => "Float",
+            Value::Boolean(_) => "Boolean",
+            Value::String(_) => "String",
+            Value::Function(_) | Value::Builtin(_) => "Function",
+            Value::Array(_) => "Array",
+            Value::Map(_) => "Map",
+            Value::Null => "Null",
+        }
+    }
+
+    pub fn new_array(elements: Vec<Value>) -> Self {
+        Value::Array(Rc::new(RefCell::new(elements)))
+    }
+
+    pub fn new_map(entries: HashMap<HashKey, Value>) -> Self {
+        Value::Map(Rc::new(RefCell::new(entries)))
+    }
+
+    /// Writes the value, with `[...]` or `{...}` in place of an array or map that `seen`
+    /// says is already being written further out, so one that contains itself still prints.
+    fn _write(&self, f: &mut fmt::Formatter<'_>, seen: &mut Vec<*const ()>) -> fmt::Result {
+        match self {
+            Value::Integer(value) => write!(f, "{}", value),
+            Value::Float(value) => write!(f, "{:?}", value),
+            Value::Boolean(true) => write!(f, "tremendous"),
+            Value::Boolean(false) => write!(f, "fake_news"),
+            Value::String(value) => write!(f, "{}", value),
+            Value::Function(function) => write!(f, "<deal {}>", function.name),
+            Value::Builtin(builtin) => write!(f, "<builtin {}>", builtin.name()),
+            Value::Array(elements) => {
+                let pointer = Rc::as_ptr(elements) as *const ();
+                if seen.contains(&pointer) {
+                    return write!(f, "[...]");
+                }
+                seen.push(pointer);
+
+                write!(f, "[")?;
+                for (i, element) in elements.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    element._write_nested(f, seen)?;
+                }
+                write!(f, "]")?;
+
+                seen.pop();
+                Ok(())
+            }
+            Value::Map(entries) => {
+                let pointer = Rc::as_ptr(entries) as *const ();
+                if seen.contains(&pointer) {
+                    return write!(f, "{{...}}");
+                }
+                seen.push(pointer);
+
+                let entries = entries.borrow();
+                let mut keys: Vec<&HashKey> = entries.keys().collect();
+                keys.sort();
+
+                write!(f, "{{")?;
+                for (i, key) in keys.into_iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    Value::from(key.clone())._write_nested(f, seen)?;
+                    write!(f, ": ")?;
+                    entries[key]._write_nested(f, seen)?;
+                }
+                write!(f, "}}")?;
+
+                seen.pop();
+                Ok(())
+            }
+            Value::Null => write!(f, "null"),
+        }
+    }
+
+    /// Writes the value as it appears inside an array or map, where strings are quoted.
+    fn _write_nested(&self, f: &mut fmt::Formatter<'_>, seen: &mut Vec<*const ()>) -> fmt::Result {
+        match self {
+            Value::String(value) => write!(f, "{:?}", value),
+            value => value._write(f, seen),
+        }
+    }
+
+    /// Compares two values. `comparing` holds the pairs of arrays or maps already being
+    /// compared further out, which are taken to be equal when they come up again, so
+    /// comparing values that contain themselves still finishes.
+    fn _equals(&self, other: &Value, comparing: &mut Vec<(*const (), *const ())>) -> bool {
+        match (self, other) {
+            (Value::Integer(l), Value::Integer(r)) => l == r,
+            (Value::Float(l), Value::Float(r)) => l == r,
+            // Promoted like the scalar `==`, so that `[1] == [1.0]` agrees with `1 == 1.0`
+            (Value::Integer(l), Value::Float(r)) => *l as f64 == *r,
+            (Value::Float(l), Value::Integer(r)) => *l == *r as f64,
+            (Value::Boolean(l), Value::Boolean(r)) => l == r,
+            (Value::String(l), Value::String(r)) => l == r,
+            (Value::Function(l), Value::Function(r)) => l == r,
+            (Value::Builtin(l), Value::Builtin(r)) => l == r,
+            (Value::Array(l), Value::Array(r)) => {
+                let pair = (Rc::as_ptr(l) as *const (), Rc::as_ptr(r) as *const ());
+                if Rc::ptr_eq(l, r) || comparing.contains(&pair) {
+                    return true;
+                }
+
+                let (l, r) = (l.borrow(), r.borrow());
+                comparing.push(pair);
+                let equal = l.len() == r.len()
+                    && l.iter().zip(r.iter()).all(|(l, r)| l._equals(r, comparing));
+                comparing.pop();
+                equal
+            }
+            (Value::Map(l), Value::Map(r)) => {
+                let pair = (Rc::as_ptr(l) as *const (), Rc::as_ptr(r) as *const ());
+                if Rc::ptr_eq(l, r) || comparing.contains(&pair) {
+                    return true;
+                }
+
+                let (l, r) = (l.borrow(), r.borrow());
+                comparing.push(pair);
+                let equal = l.len() == r.len()
+                    && l.iter()
+                        .all(|(key, l)| r.get(key).is_some_and(|r| l._equals(r, comparing)));
+                comparing.pop();
+                equal
+            }
+            (Value::Null, Value::Null) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Arrays and maps are equal when their elements are, and are always equal to themselves.
+/// Integers and floats are compared as floats, the same way `==` compares them.
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        self._equals(other, &mut Vec::new())
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self._write(f, &mut Vec::new())
+    }
+}
+
+/// The values that can be used as map keys. Floats can't, since `NaN != NaN` would make
+/// some keys impossible to look up again.
+#[derive(Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Clone)]
+pub enum HashKey {
+    Integer(i64),
+    String(String),
+    Boolean(bool),
+}
+
+impl TryFrom<&Value> for HashKey {
+    type Error = RuntimeError;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Integer(value) => Ok(HashKey::Integer(*value)),
+            Value::String(value) => Ok(HashKey::String(value.clone())),
+            Value::Boolean(value) => Ok(HashKey::Boolean(*value)),
+            other => Err(RuntimeError::UnhashableKey(other.type_name())),
+        }
+    }
+}
+
+impl From<HashKey> for Value {
+    fn from(key: HashKey) -> Self {
+        match key {
+            HashKey::Integer(value) => Value::Integer(value),
+            HashKey::String(value) => Value::String(value),
+            HashKey::Boolean(value) => Value::Boolean(value),
+        }
+    }
+}
+
+/// A `deal` together with the scope it was declared in, which it keeps seeing after that
+/// scope's own call has returned.
+pub struct Function {
+    pub name: String,
+    pub parameters: Vec<String>,
+    pub body: BlockStatement,
+    pub env: Rc<RefCell<Environment>>,
+}
+
+// The scope usually holds the function itself, so printing it would never end
+impl fmt::Debug for Function {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Function")
+            .field("name", &self.name)
+            .field("parameters", &self.parameters)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Functions are only equal to themselves.
+impl PartialEq for Function {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+/// One scope of variables, chained to the scope it was created in.
+#[derive(Debug, Default)]
+pub struct Environment {
+    variables: HashMap<String, Value>,
+    parent: Option<Rc<RefCell<Environment>>>,
+}
+
+impl Environment {
+    pub fn new() -> Self {
+        Environment {
+            variables: HashMap::new(),
+            parent: None,
+        }
+    }
+
+    pub fn enclosed(parent: Rc<RefCell<Environment>>) -> Self {
+        Environment {
+            variables: HashMap::new(),
+            parent: Some(parent),
+        }
+    }
+
+    /// Looks `name` up in this scope, then in each enclosing scope in turn.
+    pub fn get(&self, name: &str) -> Option<Value> {
+        match self.variables.get(name) {
+            Some(value) => Some(value.clone()),
+            None => self.parent.as_ref()?.borrow().get(name),
+        }
+    }
+
+    pub fn define(&mut self, name: String, value: Value) {
+        self.variables.insert(name, value);
+    }
+
+    /// Updates the nearest scope's binding of `name`. Returns `false` if nothing in the
+    /// chain defines it, since only `make` can declare a variable.
+    pub fn assign(&mut self, name: &str, value: Value) -> bool {
+        match self.variables.get_mut(name) {
+            Some(variable) => {
+                *variable = value;
+                true
+            }
+            None => match self.parent {
+                Some(ref parent) => parent.borrow_mut().assign(name, value),
+                None => false,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RuntimeError {
+    UndefinedVariable(String),
+    TypeMismatch {
+        expected: &'static str,
+        found: &'static str,
+    },
+    DivisionByZero,
+    IntegerOverflow {
+        operator: String,
+    },
+    UnsupportedOperator {
+        operator: String,
+        left: &'static str,
+        right: &'static str,
+    },
+    UnsupportedPrefixOperator {
+        operator: String,
+        operand: &'static str,
+    },
+    NotCallable(&'static str),
+    WrongArgumentCount {
+        name: String,
+        expected: usize,
+        found: usize,
+    },
+    StackOverflow,
+    ReturnOutsideFunction,
+    BreakOutsideLoop(Option<String>), // The label, if the `break` had one
+    ContinueOutsideLoop(Option<String>),
+    NotIndexable(&'static str),
+    IndexOutOfBounds {
+        index: i64,
+        length: usize,
+    },
+    UnhashableKey(&'static str),
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuntimeError::UndefinedVariable(name) => write!(f, "Undefined variable '{}'", name),
+            RuntimeError::TypeMismatch { expected, found } => {
+                write!(f, "Expected a value of type {}, found {}", expected, found)
+            }
+            RuntimeError::DivisionByZero => write!(f, "Division by zero"),
+            RuntimeError::IntegerOverflow { operator } => {
+                write!(f, "Integer overflow while evaluating '{}'", operator)
+            }
+            RuntimeError::UnsupportedOperator {
+                operator,
+                left,
+                right,
+            } => write!(
+                f,
+                "Unsupported operator '{}' for {} and {}",
+                operator, left, right
+            ),
+            RuntimeError::UnsupportedPrefixOperator { operator, operand } => {
+                write!(f, "Unsupported operator '{}' for {}", operator, operand)
+            }
+            RuntimeError::NotCallable(found) => {
+                write!(f, "A value of type {} is not callable", found)
+            }
+            RuntimeError::WrongArgumentCount {
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "'{}' takes {} argument(s) but {} were given",
+                name, expected, found
+            ),
+            RuntimeError::StackOverflow => {
+                write!(f, "Stack overflow after {} nested calls", MAX_CALL_DEPTH)
+            }
+            RuntimeError::ReturnOutsideFunction => write!(f, "Cannot return outside of a deal"),
+            RuntimeError::BreakOutsideLoop(None) => write!(f, "Cannot break outside of a loop"),
+            RuntimeError::BreakOutsideLoop(Some(label)) => {
+                write!(f, "No loop labelled '{}' to break out of", label)
+            }
+            RuntimeError::ContinueOutsideLoop(None) => {
+                write!(f, "Cannot continue outside of a loop")
+            }
+            RuntimeError::ContinueOutsideLoop(Some(label)) => {
+                write!(f, "No loop labelled '{}' to continue", label)
+            }
+            RuntimeError::NotIndexable(found) => {
+                write!(f, "A value of type {} cannot be indexed", found)
+            }
+            RuntimeError::IndexOutOfBounds { index, length } => write!(
+                f,
+                "Index {} is out of bounds for an array of length {}",
+                index, length
+            ),
+            RuntimeError::UnhashableKey(found) => {
+                write!(f, "A value of type {} cannot be used as a map key", found)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RuntimeError {}
+
+/// Why evaluation stopped before the end of the current statement list.
+enum Unwind {
+    Error(RuntimeError),
+    Return(Value),         // A `return` on its way up to the call it returns from
+    Break(Option<String>), // A `break` on its way up to its loop, which has this label if any
+    Continue(Option<String>),
+}
+
+impl Unwind {
+    /// The error to report for an `Unwind` that escaped everything that could have caught it.
+    fn into_error(self) -> RuntimeError {
+        match self {
+            Unwind::Error(error) => error,
+            Unwind::Return(_) => RuntimeError::ReturnOutsideFunction,
+            Unwind::Break(label) => RuntimeError::BreakOutsideLoop(label),
+            Unwind::Continue(label) => RuntimeError::ContinueOutsideLoop(label),
+        }
+    }
+}
+
+impl From<RuntimeError> for Unwind {
+    fn from(error: RuntimeError) -> Self {
+        Unwind::Error(error)
+    }
+}
+
+type EvalResult = Result<Value, Unwind>;
+
+#[derive(Debug, Default)]
+pub struct Interpreter {
+    env: Rc<RefCell<Environment>>, // The innermost scope
+    call_depth: usize,
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        Interpreter {
+            env: Rc::new(RefCell::new(Environment::new())),
+            call_depth: 0,
+        }
+    }
+
+    /// Evaluates every statement in order and returns the value of the last one,
+    /// or `Value::Null` for an empty program.
+    pub fn eval_program(&mut self, program: &Program) -> Result<Value, RuntimeError> {
+        let mut result = Value::Null;
+
+        for stmt in &program.statements {
+            result = self._eval_statement(stmt).map_err(Unwind::into_error)?;
+        }
+
+        Ok(result)
+    }
+
+    fn _eval_statement(&mut self, stmt: &Statement) -> EvalResult {
+        match stmt {
+            Statement::ExpressionStatement(expr_stmt) => self._eval_expression(&expr_stmt.expr),
+            Statement::LetStatement(let_stmt) => self._eval_let_statement(let_stmt),
+            Statement::BlockStatement(block) => self._eval_block_statement(block),
+            Statement::FunctionStatement(function) => self._eval_function_statement(function),
+            Statement::ReturnStatement(return_stmt) => self._eval_return_statement(return_stmt),
+            Statement::WhileStatement(while_stmt) => self._eval_while_statement(while_stmt),
+            Statement::ForStatement(for_stmt) => self._eval_for_statement(for_stmt),
+            Statement::BreakStatement(break_stmt) => {
+                Err(Unwind::Break(Self::_label_name(&break_stmt.label)))
+            }
+            Statement::ContinueStatement(continue_stmt) => {
+                Err(Unwind::Continue(Self::_label_name(&continue_stmt.label)))
+            }
+        }
+    }
+
+    fn _eval_let_statement(&mut self, let_stmt: &LetStatement) -> EvalResult {
+        let value = self._eval_expression(&let_stmt.value)?;
+        self.env
+            .borrow_mut()
+            .define(let_stmt.name.value.clone(), value);
+        Ok(Value::Null)
+    }
+
+    /// Evaluates the block in a new scope nested in the current one, so variables made
+    /// inside it are gone once it ends.
+    fn _eval_block_statement(&mut self, block: &BlockStatement) -> EvalResult {
+        let scope = Environment::enclosed(Rc::clone(&self.env));
+        self._eval_block_in_scope(block, scope)
+    }
+
+    /// Evaluates the block's statements in `scope` and returns the value of the last one.
+    fn _eval_block_in_scope(&mut self, block: &BlockStatement, scope: Environment) -> EvalResult {
+        let outer_env = std::mem::replace(&mut self.env, Rc::new(RefCell::new(scope)));
+
+        let mut result = Ok(Value::Null);
+        for stmt in &block.statements {
+            result = self._eval_statement(stmt);
+            if result.is_err() {
+                break;
+            }
+        }
+
+        self.env = outer_env;
+        result
+    }
+
+    /// Evaluates the branch the condition picks, or `Value::Null` if there's no `else`
+    /// to fall back on.
+    fn _eval_if_expression(&mut self, if_expr: &IfExpression) -> EvalResult {
+        let condition = self._eval_expression(&if_expr.condition)?;
+
+        if Self::_expect_boolean(condition)? {
+            self._eval_block_statement(&if_expr.consequence)
+        } else {
+            match if_expr.alternative {
+                Some(ElseBranch::BlockStatement(ref block)) => self._eval_block_statement(block),
+                Some(ElseBranch::IfExpression(ref if_expr)) => self._eval_if_expression(if_expr),
+                None => Ok(Value::Null),
+            }
+        }
+    }
+
+    fn _eval_function_statement(&mut self, function: &FunctionStatement) -> EvalResult {
+        let value = Value::Function(Rc::new(Function {
+            name: function.name.value.clone(),
+            parameters: function
+                .parameters
+                .iter()
+                .map(|param| param.value.clone())
+                .collect(),
+            body: function.body.clone(),
+            env: Rc::clone(&self.env),
+        }));
+
+        self.env
+            .borrow_mut()
+            .define(function.name.value.clone(), value);
+        Ok(Value::Null)
+    }
+
+    fn _eval_return_statement(&mut self, return_stmt: &ReturnStatement) -> EvalResult {
+        let value = match return_stmt.value {
+            Some(ref value) => self._eval_expression(value)?,
+            None => Value::Null,
+        };
+
+        Err(Unwind::Return(value))
+    }
+
+    /// Looks the name up in the current scope and the ones around it, and then among the
+    /// built-ins.
+    fn _eval_identifier(&self, name: &str) -> EvalResult {
+        if let Some(value) = self.env.borrow().get(name) {
+            return Ok(value);
+        }
+
+        Builtin::lookup(name)
+            .map(Value::Builtin)
+            .ok_or_else(|| RuntimeError::UndefinedVariable(name.to_string()).into())
+    }
+
+    /// Calls a function in a new scope enclosed by the one it was declared in. The call
+    /// evaluates to whatever it `return`s, or else to the value of its body's last statement.
+    fn _eval_call_expression(&mut self, call_expr: &CallExpression) -> EvalResult {
+        let function = match self._eval_expression(&call_expr.function)? {
+            Value::Function(function) => function,
+            Value::Builtin(builtin) => return self._eval_builtin_call(builtin, call_expr),
+            other => return Err(RuntimeError::NotCallable(other.type_name()).into()),
+        };
+
+        if call_expr.arguments.len() != function.parameters.len() {
+            return Err(RuntimeError::WrongArgumentCount {
+                name: function.name.clone(),
+                expected: function.parameters.len(),
+                found: call_expr.arguments.len(),
+            }
+            .into());
+        }
+
+        let mut scope = Environment::enclosed(Rc::clone(&function.env));
+        for (param, arg) in function.parameters.iter().zip(&call_expr.arguments) {
+            let value = self._eval_expression(arg)?;
+            scope.define(param.clone(), value);
+        }
+
+        if self.call_depth >= MAX_CALL_DEPTH {
+            return Err(RuntimeError::StackOverflow.into());
+        }
+
+        self.call_depth += 1;
+        let result = self._eval_block_in_scope(&function.body, scope);
+        self.call_depth -= 1;
+
+        match result {
+            Ok(value) | Err(Unwind::Return(value)) => Ok(value),
+            Err(Unwind::Error(error)) => Err(Unwind::Error(error)),
+            // Loops can't be broken out of from inside a function called in them
+            Err(unwind) => Err(unwind.into_error().into()),
+        }
+    }
+
+    fn _eval_builtin_call(&mut self, builtin: Builtin, call_expr: &CallExpression) -> EvalResult {
+        if call_expr.arguments.len() != builtin.arity() {
+            return Err(RuntimeError::WrongArgumentCount {
+                name: builtin.name().to_string(),
+                expected: builtin.arity(),
+                found: call_expr.arguments.len(),
+            }
+            .into());
+        }
+
+        let mut arguments = Vec::with_capacity(call_expr.arguments.len());
+        for arg in &call_expr.arguments {
+            arguments.push(self._eval_expression(arg)?);
+        }
+
+        Ok(builtin.call(arguments)?)
+    }
+
+    fn _eval_while_statement(&mut self, while_stmt: &WhileStatement) -> EvalResult {
+        loop {
+            let condition = self._eval_expression(&while_stmt.condition)?;
+            if !Self::_expect_boolean(condition)? {
+                break;
+            }
+
+            let result = self._eval_block_statement(&while_stmt.body);
+            if !Self::_continue_loop(&while_stmt.label, result)? {
+                break;
+            }
+        }
+
+        Ok(Value::Null)
+    }
+
+    /// Runs the body once for every integer from `start` up to but not including `end`,
+    /// each time in a new scope holding the loop variable.
+    fn _eval_for_statement(&mut self, for_stmt: &ForStatement) -> EvalResult {
+        let start = Self::_expect_integer(self._eval_expression(&for_stmt.start)?)?;
+        let end = Self::_expect_integer(self._eval_expression(&for_stmt.end)?)?;
+
+        for i in start..end {
+            let mut scope = Environment::enclosed(Rc::clone(&self.env));
+            scope.define(for_stmt.variable.value.clone(), Value::Integer(i));
+
+            let result = self._eval_block_in_scope(&for_stmt.body, scope);
+            if !Self::_continue_loop(&for_stmt.label, result)? {
+                break;
+            }
+        }
+
+        Ok(Value::Null)
+    }
+
+    /// Decides what a loop does once its body has run: `Ok(true)` to go around again,
+    /// `Ok(false)` to stop, or the `Err` to pass on when it's meant for something further out.
+    fn _continue_loop(label: &Option<Identifier>, result: EvalResult) -> Result<bool, Unwind> {
+        let is_this_loop = |target: &Option<String>| match target {
+            None => true,
+            Some(target) => label.as_ref().is_some_and(|label| &label.value == target),
+        };
+
+        match result {
+            Ok(_) => Ok(true),
+            Err(Unwind::Break(target)) if is_this_loop(&target) => Ok(false),
+            Err(Unwind::Continue(target)) if is_this_loop(&target) => Ok(true),
+            Err(unwind) => Err(unwind),
+        }
+    }
+
+    fn _eval_expression(&mut self, expr: &Expression) -> EvalResult {
+        match expr {
+            Expression::PrefixExpression(prefix_expr) => self._eval_prefix_expression(prefix_expr),
+            Expression::InfixExpression(infix_expr) => self._eval_infix_expression(infix_expr),
+            Expression::CallExpression(call_expr) => self._eval_call_expression(call_expr),
+            Expression::IfExpression(if_expr) => self._eval_if_expression(if_expr),
+            Expression::Identifier(ident) => self._eval_identifier(&ident.value),
+            Expression::IntegerLiteral(int_lit) => Ok(Value::Integer(int_lit.value)),
+            Expression::FloatLiteral(float_lit) => Ok(Value::Float(float_lit.value)),
+            Expression::BooleanLiteral(bool_lit) => Ok(Value::Boolean(bool_lit.value)),
+            Expression::StringLiteral(string_lit) => Ok(Value::String(string_lit.value.clone())),
+            Expression::StringInterpolation(interpolation) => {
+                self._eval_string_interpolation(interpolation)
+            }
+            Expression::ArrayLiteral(array_lit) => self._eval_array_literal(array_lit),
+            Expression::MapLiteral(map_lit) => self._eval_map_literal(map_lit),
+            Expression::IndexExpression(index_expr) => self._eval_index_expression(index_expr),
+            Expression::AssignExpression(assign_expr) => self._eval_assign_expression(assign_expr),
+        }
+    }
+
+    fn _eval_string_interpolation(&mut self, interpolation: &StringInterpolation) -> EvalResult {
+        let mut result = String::new();
+
+        for part in &interpolation.parts {
+            match part {
+                InterpolationPart::StringLiteral(literal) => result += &literal.value,
+                InterpolationPart::Expression(expr) => {
+                    result += &self._eval_expression(expr)?.to_string()
+                }
+            }
+        }
+
+        Ok(Value::String(result))
+    }
+
+    fn _eval_array_literal(&mut self, array_lit: &ArrayLiteral) -> EvalResult {
+        let mut elements = Vec::with_capacity(array_lit.elements.len());
+
+        for element in &array_lit.elements {
+            elements.push(self._eval_expression(element)?);
+        }
+
+        Ok(Value::new_array(elements))
+    }
+
+    /// Evaluates each key and then its value, in source order. A repeated key keeps the
+    /// last value given for it.
+    fn _eval_map_literal(&mut self, map_lit: &MapLiteral) -> EvalResult {
+        let mut entries = HashMap::with_capacity(map_lit.pairs.len());
+
+        for (key, value) in &map_lit.pairs {
+            let key = HashKey::try_from(&self._eval_expression(key)?)?;
+            let value = self._eval_expression(value)?;
+            entries.insert(key, value);
+        }
+
+        Ok(Value::new_map(entries))
+    }
+
+    fn _eval_index_expression(&mut self, index_expr: &IndexExpression) -> EvalResult {
+        let left = self._eval_expression(&index_expr.left_node)?;
+        let index = self._eval_expression(&index_expr.index)?;
+        Ok(Self::_index_value(&left, &index)?)
+    }
+
+    fn _index_value(left: &Value, index: &Value) -> Result<Value, RuntimeError> {
+        match left {
+            Value::Array(elements) => {
+                let elements = elements.borrow();
+                let position = Self::_array_position(index.clone(), elements.len())?;
+                Ok(elements[position].clone())
+            }
+            // Looking up a missing key gives null, so `m[k] == null` checks for it
+            Value::Map(entries) => {
+                let key = HashKey::try_from(index)?;
+                Ok(entries.borrow().get(&key).cloned().unwrap_or(Value::Null))
+            }
+            other => Err(RuntimeError::NotIndexable(other.type_name())),
+        }
+    }
+
+    fn _eval_assign_expression(&mut self, assign_expr: &AssignExpression) -> EvalResult {
+        match assign_expr.target {
+            AssignTarget::Identifier(ref target) => {
+                self._eval_variable_assignment(&target.value, assign_expr)
+            }
+            AssignTarget::IndexExpression(ref target) => {
+                self._eval_index_assignment(target, assign_expr)
+            }
+        }
+    }
+
+    /// Updates the nearest binding of `name`. A compound assignment reads the variable
+    /// before evaluating the right side, so `x += f()` adds to the `x` from before the call.
+    fn _eval_variable_assignment(
+        &mut self,
+        name: &str,
+        assign_expr: &AssignExpression,
+    ) -> EvalResult {
+        let current = match assign_expr.operator {
+            Some(operator) => Some((operator, self._eval_identifier(name)?)),
+            None => None,
+        };
+
+        let mut value = self._eval_expression(&assign_expr.value)?;
+        if let Some((operator, current)) = current {
+            value = Self::_eval_infix_operator(operator.binary_op(), current, value)?;
+        }
+
+        if self.env.borrow_mut().assign(name, value.clone()) {
+            Ok(value)
+        } else {
+            Err(RuntimeError::UndefinedVariable(name.to_string()).into())
+        }
+    }
+
+    /// Evaluates the array or map, then the index, then the new value, and stores the value
+    /// in the array or map. Assigning to a missing map key inserts it, but a compound
+    /// assignment to one fails like `null + 1` would.
+    fn _eval_index_assignment(
+        &mut self,
+        target: &IndexExpression,
+        assign_expr: &AssignExpression,
+    ) -> EvalResult {
+        let left = self._eval_expression(&target.left_node)?;
+        let index = self._eval_expression(&target.index)?;
+
+        let current = match assign_expr.operator {
+            Some(operator) => Some((operator, Self::_index_value(&left, &index)?)),
+            None => None,
+        };
+
+        let mut value = self._eval_expression(&assign_expr.value)?;
+        if let Some((operator, current)) = current {
+            value = Self::_eval_infix_operator(operator.binary_op(), current, value)?;
+        }
+
+        match left {
+            Value::Array(elements) => {
+                let mut elements = elements.borrow_mut();
+                let position = Self::_array_position(index, elements.len())?;
+                elements[position] = value.clone();
+                Ok(value)
+            }
+            Value::Map(entries) => {
+                let key = HashKey::try_from(&index)?;
+                entries.borrow_mut().insert(key, value.clone());
+                Ok(value)
+            }
+            other => Err(RuntimeError::NotIndexable(other.type_name()).into()),
+        }
+    }
+
+    /// Turns an index into a position in an array of `length` elements, as long as it's
+    /// an integer in bounds.
+    fn _array_position(index: Value, length: usize) -> Result<usize, RuntimeError> {
+        let index = Self::_expect_integer(index)?;
+
+        usize::try_from(index)
+            .ok()
+            .filter(|&position| position < length)
+            .ok_or(RuntimeError::IndexOutOfBounds { index, length })
+    }
+
+    fn _eval_prefix_expression(&mut self, prefix_expr: &PrefixExpression) -> EvalResult {
+        let right = self._eval_expression(&prefix_expr.right_node)?;
+        Ok(Self::_eval_prefix_operator(prefix_expr.operator, right)?)
+    }
+
+    fn _eval_prefix_operator(operator: UnaryOp, right: Value) -> Result<Value, RuntimeError> {
+        match (operator, right) {
+            (UnaryOp::Negate, Value::Integer(value)) => value
+                .checked_neg()
+                .map(Value::Integer)
+                .ok_or_else(|| RuntimeError::IntegerOverflow {
+                    operator: operator.to_string(),
+                }),
+            (UnaryOp::Negate, Value::Float(value)) => Ok(Value::Float(-value)),
+            (UnaryOp::Not, Value::Boolean(value)) => Ok(Value::Boolean(!value)),
+            (_, right) => Err(RuntimeError::UnsupportedPrefixOperator {
+                operator: operator.to_string(),
+                operand: right.type_name(),
+            }),
+        }
+    }
+
+    fn _eval_infix_expression(&mut self, infix_expr: &InfixExpression) -> EvalResult {
+        if let BinaryOp::And | BinaryOp::Or = infix_expr.operator {
+            return self._eval_logical_expression(infix_expr);
+        }
+
+        let left = self._eval_expression(&infix_expr.left_node)?;
+        let right = self._eval_expression(&infix_expr.right_node)?;
+        Ok(Self::_eval_infix_operator(
+            infix_expr.operator,
+            left,
+            right,
+        )?)
+    }
+
+    fn _eval_infix_operator(
+        operator: BinaryOp,
+        left: Value,
+        right: Value,
+    ) -> Result<Value, RuntimeError> {
+        match (left, right) {
+            (Value::Integer(l), Value::Integer(r)) => Self::_eval_integer_infix(operator, l, r),
+            (Value::Integer(l), Value::Float(r)) => Self::_eval_float_infix(operator, l as f64, r),
+            (Value::Float(l), Value::Integer(r)) => Self::_eval_float_infix(operator, l, r as f64),
+            (Value::Float(l), Value::Float(r)) => Self::_eval_float_infix(operator, l, r),
+            (Value::String(l), Value::String(r)) => Self::_eval_string_infix(operator, l, r),
+            (l, r) if operator == BinaryOp::Equal => Ok(Value::Boolean(l == r)),
+            (l, r) if operator == BinaryOp::NotEqual => Ok(Value::Boolean(l != r)),
+            (l, r) => Err(RuntimeError::UnsupportedOperator {
+                operator: operator.to_string(),
+                left: l.type_name(),
+                right: r.type_name(),
+            }),
+        }
+    }
+
+    /// Evaluates `&&` and `||`, only evaluating the right operand when the left one
+    /// doesn't already decide the result.
+    fn _eval_logical_expression(&mut self, infix_expr: &InfixExpression) -> EvalResult {
+        let left = self._eval_expression(&infix_expr.left_node)?;
+        let left = Self::_expect_boolean(left)?;
+
+        match (infix_expr.operator, left) {
+            (BinaryOp::And, false) | (BinaryOp::Or, true) => Ok(Value::Boolean(left)),
+            _ => {
+                let right = self._eval_expression(&infix_expr.right_node)?;
+                Ok(Value::Boolean(Self::_expect_boolean(right)?))
+            }
+        }
+    }
+
+    fn _expect_boolean(value: Value) -> Result<bool, RuntimeError> {
+        match value {
+            Value::Boolean(value) => Ok(value),
+            other => Err(RuntimeError::TypeMismatch {
+                expected: "Boolean",
+                found: other.type_name(),
+            }),
+        }
+    }
+
+    fn _label_name(label: &Option<Identifier>) -> Option<String> {
+        label.as_ref().map(|label| label.value.clone())
+    }
+
+    fn _expect_integer(value: Value) -> Result<i64, RuntimeError> {
+        match value {
+            Value::Integer(value) => Ok(value),
+            other => Err(RuntimeError::TypeMismatch {
+                expected: "Integer",
+                found: other.type_name(),
+            }),
+        }
+    }
+
+    /// Evaluates a comparison operator given how its operands compare, or returns `None`
+    /// if `operator` isn't a comparison.
+    fn _eval_comparison(operator: BinaryOp, ordering: Option<Ordering>) -> Option<Value> {
+        let result = match operator {
+            BinaryOp::Equal => ordering == Some(Ordering::Equal),
+            BinaryOp::NotEqual => ordering != Some(Ordering::Equal),
+            BinaryOp::LessThan => ordering == Some(Ordering::Less),
+            BinaryOp::GreaterThan => ordering == Some(Ordering::Greater),
+            BinaryOp::LessEqual => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+            BinaryOp::GreaterEqual => {
+                matches!(ordering, Some(Ordering::Greater | Ordering::Equal))
+            }
+            BinaryOp::Add
+            | BinaryOp::Subtract
+            | BinaryOp::Multiply
+            | BinaryOp::Divide
+            | BinaryOp::Modulo
+            | BinaryOp::Power
+            | BinaryOp::And
+            | BinaryOp::Or => return None,
+        };
+
+        Some(Value::Boolean(result))
+    }
+
+    fn _eval_integer_infix(
+        operator: BinaryOp,
+        left: i64,
+        right: i64,
+    ) -> Result<Value, RuntimeError> {
+        if let Some(result) = Self::_eval_comparison(operator, Some(left.cmp(&right))) {
+            return Ok(result);
+        }
+
+        let overflow = || RuntimeError::IntegerOverflow {
+            operator: operator.to_string(),
+        };
+
+        let value = match operator {
+            BinaryOp::Add => left.checked_add(right).ok_or_else(overflow)?,
+            BinaryOp::Subtract => left.checked_sub(right).ok_or_else(overflow)?,
+            BinaryOp::Multiply => left.checked_mul(right).ok_or_else(overflow)?,
+            BinaryOp::Divide | BinaryOp::Modulo if right == 0 => {
+                return Err(RuntimeError::DivisionByZero)
+            }
+            BinaryOp::Divide => left.checked_div(right).ok_or_else(overflow)?,
+            BinaryOp::Modulo => left.checked_rem(right).ok_or_else(overflow)?,
+            // A negative exponent can't produce an integer, so promote to float
+            BinaryOp::Power if right < 0 => {
+                return Self::_eval_float_infix(operator, left as f64, right as f64)
+            }
+            BinaryOp::Power => match (left, u32::try_from(right)) {
+                (_, Ok(exp)) => left.checked_pow(exp).ok_or_else(overflow)?,
+                // The exponent is too large for `checked_pow`, but these bases don't grow
+                (0, Err(_)) => 0,
+                (1, Err(_)) => 1,
+                (-1, Err(_)) if right % 2 == 0 => 1,
+                (-1, Err(_)) => -1,
+                (_, Err(_)) => return Err(overflow()),
+            },
+            // Comparisons were evaluated above, and `&&` and `||` only take booleans
+            BinaryOp::Equal
+            | BinaryOp::NotEqual
+            | BinaryOp::LessThan
+            | BinaryOp::GreaterThan
+            | BinaryOp::LessEqual
+            | BinaryOp::GreaterEqual
+            | BinaryOp::And
+            | BinaryOp::Or => {
+                return Err(RuntimeError::UnsupportedOperator {
+                    operator: operator.to_string(),
+                    left: "Integer",
+                    right: "Integer",
+                })
+            }
+        };
+
+        Ok(Value::Integer(value))
+    }
+
+    fn _eval_string_infix(
+        operator: BinaryOp,
+        left: String,
+        right: String,
+    ) -> Result<Value, RuntimeError> {
+        if let Some(result) = Self::_eval_comparison(operator, Some(left.cmp(&right))) {
+            return Ok(result);
+        }
+
+        match operator {
+            BinaryOp::Add => Ok(Value::String(left + &right)),
+            BinaryOp::Subtract
+            | BinaryOp::Multiply
+            | BinaryOp::Divide
+            | BinaryOp::Modulo
+            | BinaryOp::Power
+            | BinaryOp::Equal
+            | BinaryOp::NotEqual
+            | BinaryOp::LessThan
+            | BinaryOp::GreaterThan
+            | BinaryOp::LessEqual
+            | BinaryOp::GreaterEqual
+            | BinaryOp::And
+            | BinaryOp::Or => Err(RuntimeError::UnsupportedOperator {
+                operator: operator.to_string(),
+                left: "String",
+                right: "String",
+            }),
+        }
+    }
+
+    fn _eval_float_infix(operator: BinaryOp, left: f64, right: f64) -> Result<Value, RuntimeError> {
+        if let Some(result) = Self::_eval_comparison(operator, left.partial_cmp(&right)) {
+            return Ok(result);
+        }
+
+        let value = match operator {
+            BinaryOp::Add => left + right,
+            BinaryOp::Subtract => left - right,
+            BinaryOp::Multiply => left * right,
+            BinaryOp::Divide => left / right,
+            BinaryOp::Modulo => left % right,
+            BinaryOp::Power => left.powf(right),
+            // Comparisons were evaluated above, and `&&` and `||` only take booleans
+            BinaryOp::Equal
+            | BinaryOp::NotEqual
+            | BinaryOp::LessThan
+            | BinaryOp::GreaterThan
+            | BinaryOp::LessEqual
+            | BinaryOp::GreaterEqual
+            | BinaryOp::And
+            | BinaryOp::Or => {
+                return Err(RuntimeError::UnsupportedOperator {
+                    operator: operator.to_string(),
+                    left: "Float",
+                    right: "Float",
+                })
+            }
+        };
+
+        Ok(Value::Float(value))
+    }
+}
@@ -1,87 +1,343 @@
-use crate::token::{Token, TokenType};
+use crate::token::{lookup_identifier, Span, Token, TokenType, Trivia, TriviaKind};
+use std::fmt;
+use unicode_ident::{is_xid_continue, is_xid_start};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum LexErrorKind {
+    UnexpectedCharacter,      // The character can't start any token
+    UnterminatedString,       // A string literal is missing its closing `"`
+    UnclosedInterpolation,    // A `{` in a string literal is missing its closing `}`
+    UnterminatedBlockComment, // A block comment is missing its closing `*/`
+    MalformedNumber,          // A numeric literal has no digits, bad digits or two fractions
+    InvalidNumberSuffix,      // A numeric literal ends in something other than `i64`/`f64`
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct LexError {
+    pub kind: LexErrorKind,
+    pub lexeme: String, // The source text of the offending `Illegal` token
+    pub span: Span,     // Where the offending token is in the source
+}
+
+impl LexError {
+    pub fn new(kind: LexErrorKind, lexeme: String, span: Span) -> Self {
+        Self { kind, lexeme, span }
+    }
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            LexErrorKind::UnexpectedCharacter => write!(f, "Unknown character '{}'", self.lexeme),
+            LexErrorKind::UnterminatedString => write!(f, "Unterminated string literal"),
+            LexErrorKind::UnclosedInterpolation => {
+                write!(f, "Unclosed interpolation in string literal")
+            }
+            LexErrorKind::UnterminatedBlockComment => write!(f, "Unterminated block comment"),
+            LexErrorKind::MalformedNumber => write!(f, "Malformed number '{}'", self.lexeme),
+            LexErrorKind::InvalidNumberSuffix => {
+                write!(f, "Invalid suffix on number '{}'", self.lexeme)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LexError {}
+
+/// Lexes the whole source, `Eof` token included, failing on the first invalid token.
+pub fn tokenize(source: &str) -> Result<Vec<Token>, LexError> {
+    let mut lexer = Lexer::new(source);
+    let tokens: Vec<Token> = lexer.by_ref().collect();
+
+    match lexer.errors.into_iter().next() {
+        Some(error) => Err(error),
+        None => Ok(tokens),
+    }
+}
 
 #[derive(Debug)]
 pub struct Lexer<'a> {
-    pub source: &'a str,            // Reference to the source code
-    pub position: usize,            // The current position (0 initially)
-    pub read_position: usize,       // The next position in the input
-    pub line_no: usize,             // The current line number (1 initially)
-    pub current_char: Option<char>, // The current character being processed
+    source: &'a str,            // Reference to the source code
+    offset: usize,              // The byte offset of the current character (0 initially)
+    line_no: usize,             // The current line number (1 initially)
+    column: usize,              // The number of characters before the current one on its line
+    current_char: Option<char>, // The current character being processed
+    token_start: Span,          // Where the token being read starts
+    keep_doc_comments: bool,    // Whether doc comments are attached to the next token
+    doc_comments: Vec<Trivia>,  // Doc comments waiting for the next token
+    errors: Vec<LexError>,      // One error for every `Illegal` token produced so far
+    finished: bool,             // Whether the iterator has already yielded `Eof`
 }
 
 impl<'a> Lexer<'a> {
     pub fn new(source: &'a str) -> Self {
         let mut lexer = Lexer {
             source,
-            position: 0,
-            read_position: 0,
+            offset: 0,
             line_no: 1,
+            column: 0,
             current_char: None,
+            token_start: Span::new(1, 0, 0, 0),
+            keep_doc_comments: false,
+            doc_comments: Vec::new(),
+            errors: Vec::new(),
+            finished: false,
         };
 
         lexer._read_char();
         lexer
     }
 
+    /// Creates a lexer that keeps `///` and `/** */` doc comments as trivia on the token
+    /// that follows them, for tools like formatters and doc generators.
+    pub fn with_doc_comments(source: &'a str) -> Self {
+        let mut lexer = Lexer::new(source);
+        lexer.keep_doc_comments = true;
+        lexer
+    }
+
+    pub fn errors(&self) -> &[LexError] {
+        &self.errors
+    }
+
+    /// Returns the next token. Once the source is exhausted this keeps returning `Eof`;
+    /// use the `Iterator` impl to stop after the first one instead.
     pub fn next_token(&mut self) -> Token {
-        self._skip_whitespace();
+        let mut token = match self._skip_trivia() {
+            Some(unterminated_comment) => unterminated_comment,
+            None => self._read_token(),
+        };
 
-        let token = match self.current_char {
+        token.trivia = std::mem::take(&mut self.doc_comments);
+        token
+    }
+
+    fn _read_token(&mut self) -> Token {
+        self._mark_token_start();
+
+        match self.current_char {
+            Some('+') if self._peek_char() == Some('=') => {
+                self._create_double_char_token(TokenType::PlusAssign)
+            }
             Some('+') => self._create_single_char_token(TokenType::Plus),
+            Some('-') if self._peek_char() == Some('=') => {
+                self._create_double_char_token(TokenType::MinusAssign)
+            }
             Some('-') => self._create_single_char_token(TokenType::Minus),
+            Some('*') if self._peek_char() == Some('=') => {
+                self._create_double_char_token(TokenType::AsteriskAssign)
+            }
             Some('*') => self._create_single_char_token(TokenType::Asterisk),
+            Some('/') if self._peek_char() == Some('=') => {
+                self._create_double_char_token(TokenType::SlashAssign)
+            }
             Some('/') => self._create_single_char_token(TokenType::Slash),
+            Some('^') if self._peek_char() == Some('=') => {
+                self._create_double_char_token(TokenType::PowAssign)
+            }
             Some('^') => self._create_single_char_token(TokenType::Pow),
+            Some('%') if self._peek_char() == Some('=') => {
+                self._create_double_char_token(TokenType::ModulusAssign)
+            }
             Some('%') => self._create_single_char_token(TokenType::Modulus),
+            Some('!') if self._peek_char() == Some('=') => {
+                self._create_double_char_token(TokenType::NotEqual)
+            }
+            Some('!') => self._create_single_char_token(TokenType::Bang),
+            Some('=') if self._peek_char() == Some('=') => {
+                self._create_double_char_token(TokenType::Equal)
+            }
+            Some('=') => self._create_single_char_token(TokenType::Assign),
+            Some('<') if self._peek_char() == Some('=') => {
+                self._create_double_char_token(TokenType::LessEqual)
+            }
+            Some('<') => self._create_single_char_token(TokenType::LessThan),
+            Some('>') if self._peek_char() == Some('=') => {
+                self._create_double_char_token(TokenType::GreaterEqual)
+            }
+            Some('>') => self._create_single_char_token(TokenType::GreaterThan),
+            Some('&') if self._peek_char() == Some('&') => {
+                self._create_double_char_token(TokenType::And)
+            }
+            Some('|') if self._peek_char() == Some('|') => {
+                self._create_double_char_token(TokenType::Or)
+            }
             Some('(') => self._create_single_char_token(TokenType::LeftParen),
             Some(')') => self._create_single_char_token(TokenType::RightParen),
             Some(';') => self._create_single_char_token(TokenType::SemiColon),
-            Some(ch) if ch.is_ascii_digit() || ch == '.' => {
-                let lexeme = self._read_number();
-                if lexeme.contains('.') {
-                    self._new_token(TokenType::Float, lexeme)
-                } else {
-                    self._new_token(TokenType::Integer, lexeme)
-                }
+            Some(':') => self._create_single_char_token(TokenType::Colon),
+            Some(',') => self._create_single_char_token(TokenType::Comma),
+            Some('.') if self._peek_char() == Some('.') => {
+                self._create_double_char_token(TokenType::DotDot)
             }
-            Some(ch) if ch.is_alphabetic() || ch == '_' => {
+            Some('{') => self._create_single_char_token(TokenType::LeftBrace),
+            Some('}') => self._create_single_char_token(TokenType::RightBrace),
+            Some('[') => self._create_single_char_token(TokenType::LeftBracket),
+            Some(']') => self._create_single_char_token(TokenType::RightBracket),
+            Some('"') => self._read_string(),
+            Some(ch) if ch.is_ascii_digit() => self._read_number(),
+            Some(ch) if is_xid_start(ch) || ch == '_' => {
                 let lexeme = self._read_identifier();
-                self._new_token(TokenType::Identifier, lexeme)
+                self._new_token(lookup_identifier(&lexeme), lexeme)
             }
             None => self._new_token(TokenType::Eof, "".to_string()),
             Some(_) => {
                 let lexeme = self.current_char.unwrap().to_string();
                 self._read_char();
-                self._new_token(TokenType::Illegal, lexeme)
+                self._illegal_token(LexErrorKind::UnexpectedCharacter, lexeme)
             }
-        };
-
-        token
+        }
     }
 
     fn _read_char(&mut self) {
-        if self.read_position >= self.source.len() {
-            self.current_char = None;
-        } else {
-            self.current_char = self.source.chars().nth(self.read_position);
+        if let Some(ch) = self.current_char {
+            self.offset += ch.len_utf8();
+            if ch == '\n' {
+                self.line_no += 1;
+                self.column = 0;
+            } else {
+                self.column += 1;
+            }
         }
 
-        self.position = self.read_position;
-        self.read_position += 1;
+        self.current_char = self.source[self.offset..].chars().next();
+    }
+
+    /// Moves back to `position`, a span that was empty at some earlier character.
+    fn _rewind(&mut self, position: Span) {
+        self.offset = position.start;
+        self.line_no = position.line;
+        self.column = position.column;
+        self.current_char = self.source[self.offset..].chars().next();
+    }
+
+    /// An empty span at the current character.
+    fn _position(&self) -> Span {
+        Span::new(self.line_no, self.column, self.offset, self.offset)
+    }
+
+    fn _mark_token_start(&mut self) {
+        self.token_start = Span::new(self.line_no, self.column, self.offset, self.offset);
+    }
+
+    /// The span from the start of the current token up to the current character.
+    fn _token_span(&self) -> Span {
+        Span {
+            end: self.offset,
+            ..self.token_start
+        }
     }
 
-    fn _skip_whitespace(&mut self) {
+    fn _peek_char(&self) -> Option<char> {
+        self._peek_char_at(0)
+    }
+
+    /// Looks `distance` characters past the one after the current character. Walking
+    /// from `offset` keeps this constant time instead of rescanning the whole source.
+    fn _peek_char_at(&self, distance: usize) -> Option<char> {
+        self.source[self.offset..].chars().nth(distance + 1)
+    }
+
+    /// Skips whitespace and comments. Returns an `Illegal` token if a block comment is
+    /// never closed.
+    fn _skip_trivia(&mut self) -> Option<Token> {
+        loop {
+            match (self.current_char, self._peek_char()) {
+                (Some(ch), _) if ch.is_whitespace() => self._read_char(),
+                (Some('/'), Some('/')) => self._read_line_comment(),
+                (Some('/'), Some('*')) => {
+                    if let Some(token) = self._read_block_comment() {
+                        return Some(token);
+                    }
+                }
+                _ => return None,
+            }
+        }
+    }
+
+    fn _read_line_comment(&mut self) {
+        self._mark_token_start();
+        let is_doc = self._peek_char_at(1) == Some('/') && self._peek_char_at(2) != Some('/');
+
+        let mut text = String::new();
         while let Some(ch) = self.current_char {
-            if ch.is_whitespace() {
-                self._read_char();
-            } else {
+            if ch == '\n' {
                 break;
             }
+            text.push(ch);
+            self._read_char();
+        }
+
+        if is_doc && self.keep_doc_comments {
+            let trivia = Trivia::new(
+                TriviaKind::LineDocComment,
+                text[3..].to_string(),
+                self._token_span(),
+            );
+            self.doc_comments.push(trivia);
         }
     }
 
+    /// Reads a (possibly nested) block comment, returning an `Illegal` token holding the
+    /// comment if it never ends.
+    fn _read_block_comment(&mut self) -> Option<Token> {
+        self._mark_token_start();
+        let is_doc = self._peek_char_at(1) == Some('*')
+            && !matches!(self._peek_char_at(2), Some('*') | Some('/'));
+
+        let mut text = String::new();
+        let mut depth = 0;
+        while let Some(ch) = self.current_char {
+            match (ch, self._peek_char()) {
+                ('/', Some('*')) => {
+                    depth += 1;
+                    text.push_str("/*");
+                    self._read_char();
+                    self._read_char();
+                }
+                ('*', Some('/')) => {
+                    depth -= 1;
+                    text.push_str("*/");
+                    self._read_char();
+                    self._read_char();
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                _ => {
+                    text.push(ch);
+                    self._read_char();
+                }
+            }
+        }
+
+        if depth > 0 {
+            return Some(self._illegal_token(LexErrorKind::UnterminatedBlockComment, text));
+        }
+
+        if is_doc && self.keep_doc_comments {
+            let contents = text[3..text.len() - 2].to_string();
+            let trivia = Trivia::new(TriviaKind::BlockDocComment, contents, self._token_span());
+            self.doc_comments.push(trivia);
+        }
+
+        None
+    }
+
     fn _new_token(&self, token_type: TokenType, lexeme: String) -> Token {
-        Token::new(token_type, lexeme, self.line_no, self.position)
+        Token::new(token_type, lexeme, self._token_span())
+    }
+
+    fn _illegal_token(&mut self, kind: LexErrorKind, lexeme: String) -> Token {
+        self._illegal_token_at(kind, lexeme, self._token_span())
+    }
+
+    /// Like `_illegal_token`, but reported at `span` instead of the whole token.
+    fn _illegal_token_at(&mut self, kind: LexErrorKind, lexeme: String, span: Span) -> Token {
+        let token = Token::new(TokenType::Illegal, lexeme, span);
+        self.errors
+            .push(LexError::new(kind, token.lexeme.clone(), token.span));
+        token
     }
 
     fn _create_single_char_token(&mut self, token_type: TokenType) -> Token {
@@ -90,27 +346,230 @@ impl<'a> Lexer<'a> {
         self._new_token(token_type, lexeme)
     }
 
-    fn _read_number(&mut self) -> String {
-        let start_position = self.position;
+    fn _create_double_char_token(&mut self, token_type: TokenType) -> Token {
+        let start_offset = self.offset;
+        self._read_char();
+        self._read_char();
+        let lexeme = self.source[start_offset..self.offset].to_string();
+        self._new_token(token_type, lexeme)
+    }
+
+    /// Reads a numeric literal: `0x`/`0b`/`0o` integers, decimal integers and floats with an
+    /// optional exponent, all allowing `_` separators and an `i64`/`f64` type suffix. The
+    /// lexeme is kept as written; the parser turns it into a value.
+    fn _read_number(&mut self) -> Token {
+        let start_offset = self.offset;
+        let mut token_type = TokenType::Integer;
+
+        let radix = match (self.current_char, self._peek_char()) {
+            (Some('0'), Some('x' | 'X')) => 16,
+            (Some('0'), Some('o' | 'O')) => 8,
+            (Some('0'), Some('b' | 'B')) => 2,
+            _ => 10,
+        };
+
+        let has_digits = if radix == 10 {
+            self._read_digits(10);
+
+            if self.current_char == Some('.') && self._peek_is_digit(0) {
+                token_type = TokenType::Float;
+                self._read_char();
+                self._read_digits(10);
+            }
+
+            let has_exponent_sign = matches!(self._peek_char(), Some('+' | '-'));
+            if matches!(self.current_char, Some('e' | 'E'))
+                && self._peek_is_digit(usize::from(has_exponent_sign))
+            {
+                token_type = TokenType::Float;
+                self._read_char();
+                if has_exponent_sign {
+                    self._read_char();
+                }
+                self._read_digits(10);
+            }
+
+            true
+        } else {
+            self._read_char();
+            self._read_char();
+            self._read_digits(radix) > 0
+        };
+
+        let suffix_start = self.offset;
         while let Some(ch) = self.current_char {
-            if ch.is_ascii_digit() || ch == '.' {
+            if is_xid_continue(ch) {
                 self._read_char();
             } else {
                 break;
             }
         }
-        self.source[start_position..self.position].to_string()
+        let suffix = &self.source[suffix_start..self.offset];
+
+        // A second fractional part, as in `1.2.3`
+        let mut has_extra_fraction = false;
+        while self.current_char == Some('.') && self._peek_is_digit(0) {
+            has_extra_fraction = true;
+            self._read_char();
+            self._read_digits(10);
+        }
+
+        let lexeme = self.source[start_offset..self.offset].to_string();
+
+        if !has_digits || has_extra_fraction || suffix.starts_with(|ch: char| ch.is_ascii_digit()) {
+            return self._illegal_token(LexErrorKind::MalformedNumber, lexeme);
+        }
+
+        match suffix {
+            "" => {}
+            "i64" if token_type == TokenType::Integer => {}
+            "f64" if radix == 10 => token_type = TokenType::Float,
+            _ => return self._illegal_token(LexErrorKind::InvalidNumberSuffix, lexeme),
+        }
+
+        self._new_token(token_type, lexeme)
+    }
+
+    /// Reads digits of the given radix and `_` separators, returning how many digits there were.
+    fn _read_digits(&mut self, radix: u32) -> usize {
+        let mut digits = 0;
+
+        while let Some(ch) = self.current_char {
+            if ch.is_digit(radix) {
+                digits += 1;
+            } else if ch != '_' {
+                break;
+            }
+            self._read_char();
+        }
+
+        digits
     }
 
+    fn _peek_is_digit(&self, distance: usize) -> bool {
+        self._peek_char_at(distance)
+            .is_some_and(|ch| ch.is_ascii_digit())
+    }
+
+    /// Reads a string literal, quotes included. Escapes are kept as written for the parser
+    /// to decode, and quotes inside a `{...}` interpolation start a nested string, so
+    /// `"a {f("b")}"` is a single token. A literal brace is written `\{` or `\}`.
+    ///
+    /// Nested strings are tracked on a stack rather than by recursing, so no amount of
+    /// nesting can overflow the call stack.
+    ///
+    /// A `{` that's never closed makes the string's own closing quote look like the start
+    /// of a nested string, which would swallow the rest of the file. So if the string runs
+    /// off the end, the `{` that was still open when the first nested string inside it
+    /// started is reported instead, and lexing picks up again just after that quote.
+    fn _read_string(&mut self) -> Token {
+        // How many `{` are open in each string that's being read, innermost last
+        let mut interpolation_depths = vec![0];
+        // Where every `{` still open in the outermost string is
+        let mut open_braces: Vec<Span> = Vec::new();
+        // The `{` to blame, where to pick up again and how much of `lexeme` to keep, from
+        // the outermost string's first nested string whose `{` hasn't been closed yet
+        let mut unclosed: Option<(usize, Span, usize)> = None;
+        let mut lexeme = String::from('"');
+        self._read_char();
+
+        while let Some(ch) = self.current_char {
+            let outermost = interpolation_depths.len() == 1;
+            let interpolation_depth = interpolation_depths.last_mut().unwrap();
+
+            match ch {
+                '"' if *interpolation_depth == 0 => {
+                    lexeme.push(ch);
+                    self._read_char();
+                    interpolation_depths.pop();
+                    if interpolation_depths.is_empty() {
+                        return self._new_token(TokenType::String, lexeme);
+                    }
+                }
+                '"' => {
+                    lexeme.push(ch);
+                    self._read_char();
+                    interpolation_depths.push(0);
+                    if outermost && unclosed.is_none() {
+                        let brace = open_braces.len() - 1;
+                        unclosed = Some((brace, self._position(), lexeme.len()));
+                    }
+                }
+                '\\' => {
+                    lexeme.push(ch);
+                    self._read_char();
+                    if let Some(escaped) = self.current_char {
+                        lexeme.push(escaped);
+                        self._read_char();
+                    }
+                }
+                _ => {
+                    if ch == '{' {
+                        *interpolation_depth += 1;
+                        if outermost {
+                            let position = self._position();
+                            open_braces.push(Span::new(
+                                position.line,
+                                position.column,
+                                position.start,
+                                position.start + 1,
+                            ));
+                        }
+                    } else if ch == '}' && *interpolation_depth > 0 {
+                        *interpolation_depth -= 1;
+                        if outermost {
+                            open_braces.pop();
+                            if unclosed.is_some_and(|(brace, ..)| brace == open_braces.len()) {
+                                unclosed = None;
+                            }
+                        }
+                    }
+                    lexeme.push(ch);
+                    self._read_char();
+                }
+            }
+        }
+
+        match unclosed {
+            Some((brace, resume, length)) => {
+                self._rewind(resume);
+                lexeme.truncate(length);
+                self._illegal_token_at(
+                    LexErrorKind::UnclosedInterpolation,
+                    lexeme,
+                    open_braces[brace],
+                )
+            }
+            None => self._illegal_token(LexErrorKind::UnterminatedString, lexeme),
+        }
+    }
+
+    /// Reads an identifier following Unicode's XID rules (plus a leading `_`), so letters
+    /// from any script work and digits are allowed after the first character. Keywords are
+    /// told apart afterwards by `lookup_identifier`.
     fn _read_identifier(&mut self) -> String {
-        let start_position = self.position;
+        let start_offset = self.offset;
         while let Some(ch) = self.current_char {
-            if ch.is_alphabetic() || ch == '_' {
+            if is_xid_continue(ch) {
                 self._read_char();
             } else {
                 break;
             }
         }
-        self.source[start_position..self.position].to_string()
+        self.source[start_offset..self.offset].to_string()
+    }
+}
+
+impl Iterator for Lexer<'_> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        if self.finished {
+            return None;
+        }
+
+        let token = self.next_token();
+        self.finished = token.token_type == TokenType::Eof;
+        Some(token)
     }
 }
@@ -3,6 +3,10 @@
 // aka content president
 
 pub mod ast;
+pub mod builtins;
+pub mod checker;
+pub mod diagnostics;
+pub mod interpreter;
 pub mod lexer;
 pub mod parser;
 pub mod token;
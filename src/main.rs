@@ -1,39 +1,88 @@
 use std::fs;
 use std::io::Write;
+use trump::checker::Checker;
+use trump::diagnostics::Diagnostic;
+use trump::interpreter::Interpreter;
 use trump::lexer::Lexer;
 use trump::parser::Parser;
 
 const DEBUG_LEXER: bool = true;
 const DEBUG_PARSER: bool = true;
+const RUN_INTERPRETER: bool = true;
+const STACK_SIZE: usize = 64 * 1024 * 1024;
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let source_code = if let Some(file_path) = std::env::args().nth(1) {
-        fs::read_to_string(file_path)?
-    } else {
+type Error = Box<dyn std::error::Error + Send + Sync>;
+
+fn main() -> Result<(), Error> {
+    let file_path = std::env::args().nth(1).unwrap_or_else(|| {
         println!("No source file provided. Reading from 'test_files/test.trump'...");
-        fs::read_to_string("test_files/test.trump")?
-    };
+        "test_files/test.trump".to_string()
+    });
+
+    // Printing, checking and running all recurse through the tree, and every trump call
+    // nests a handful of Rust calls, so give deep programs some room
+    std::thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(move || run(&file_path))?
+        .join()
+        .expect("trump panicked")
+}
+
+fn run(file_path: &str) -> Result<(), Error> {
+    let source_code = fs::read_to_string(file_path)?;
 
     if DEBUG_LEXER {
-        let mut lexer = Lexer::new(&source_code);
-        while lexer.current_char.is_some() {
-            println!("{:?}", lexer.next_token());
+        for token in Lexer::with_doc_comments(&source_code) {
+            println!("{:?}", token);
         }
     }
 
-    if DEBUG_PARSER {
-        let mut parser = Parser::new(Lexer::new(&source_code));
+    let mut parser = Parser::new(Lexer::new(&source_code));
+    let program = parser.parse_program();
 
-        let program = parser.parse_program();
+    if DEBUG_PARSER {
+        print!("{}", program);
 
         fs::create_dir_all("debug")?;
 
         let mut file = fs::File::create("debug/ast.json")?;
-        let json = serde_json::to_string_pretty(&program.json())?;
-        file.write_all(json.as_bytes())?;
+        file.write_all(program.to_json().as_bytes())?;
 
         println!("Wrote AST to debug/ast.json successfully");
     }
 
+    if !parser.errors().is_empty() {
+        for error in parser.errors() {
+            eprintln!(
+                "{}",
+                Diagnostic::from(error).render(file_path, &source_code)
+            );
+        }
+        std::process::exit(1);
+    }
+
+    let mut checker = Checker::new();
+    checker.check_program(&program);
+
+    if !checker.errors().is_empty() {
+        for error in checker.errors() {
+            eprintln!(
+                "{}",
+                Diagnostic::from(error).render(file_path, &source_code)
+            );
+        }
+        std::process::exit(1);
+    }
+
+    if RUN_INTERPRETER {
+        match Interpreter::new().eval_program(&program) {
+            Ok(result) => println!("{}", result),
+            Err(error) => {
+                eprintln!("error: {}", error);
+                std::process::exit(1);
+            }
+        }
+    }
+
     Ok(())
 }
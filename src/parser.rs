@@ -3,38 +3,161 @@
 // you're a liar
 
 use crate::ast::{
-    Expression, ExpressionStatement, FloatLiteral, InfixExpression, IntegerLiteral, Node, Program,
-    Statement,
+    ArrayLiteral, AssignExpression, AssignOp, AssignTarget, BinaryOp, BlockStatement,
+    BooleanLiteral, BreakStatement, CallExpression, ContinueStatement, ElseBranch, Expression,
+    ExpressionStatement, FloatLiteral, ForStatement, FunctionStatement, Identifier, IfExpression,
+    IndexExpression, InfixExpression, IntegerLiteral, InterpolationPart, LetStatement, MapLiteral,
+    NodeId, PrefixExpression, Program, ReturnStatement, Statement, StringInterpolation,
+    StringLiteral, UnaryOp, WhileStatement,
 };
-use crate::lexer::Lexer;
-use crate::token::{Token, TokenType};
+use crate::lexer::{LexError, LexErrorKind, Lexer};
+use crate::token::{Span, Token, TokenType};
 use std::collections::HashMap;
+use std::fmt;
+use std::num::IntErrorKind;
 
+/// How tightly operators bind, from loosest to tightest:
+///
+/// | Precedence    | Operators              | Associativity |
+/// |---------------|------------------------|---------------|
+/// | `Assign`      | `=` `+=` `-=` `*=` ... | right         |
+/// | `Or`          | `\|\|`                 | left          |
+/// | `And`         | `&&`                   | left          |
+/// | `Equals`      | `==` `!=`              | left          |
+/// | `LessGreater` | `<` `>` `<=` `>=`      | left          |
+/// | `Sum`         | `+` `-`                | left          |
+/// | `Product`     | `*` `/` `%`            | left          |
+/// | `Power`       | `^`                    | right         |
+/// | `Prefix`      | `-x` `!x`              | -             |
+/// | `Call`        | `f(x)`                 | left          |
+/// | `Index`       | `a[i]`                 | left          |
+///
+/// So `-2 ^ 2` is `(-2) ^ 2` and `2 ^ 3 ^ 2` is `2 ^ (3 ^ 2)`.
 #[derive(Debug, PartialEq, PartialOrd, Clone, Copy)]
 pub enum PrecedenceType {
     Lowest,      // Lowest precedence (e.g., for non-operators)
+    Assign,      // =, +=, -=, *=, /=, %= or ^=
+    Or,          // ||
+    And,         // &&
     Equals,      // == or !=
-    LessGreater, // < or >
+    LessGreater, // <, >, <= or >=
     Sum,         // + or -
-    Product,     // * or /
+    Product,     // *, / or %
     Power,       // ^
     Prefix,      // -x or !x
     Call,        // Function calls, e.g., add(1, 2)
     Index,       // Array indexing, e.g., arr[0]
 }
 
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Associativity {
+    Left,  // a - b - c is (a - b) - c
+    Right, // a ^ b ^ c is a ^ (b ^ c)
+}
+
+/// How many expressions and blocks may be open inside each other before parsing gives up,
+/// so that something like ten thousand `(` is a parse error instead of a stack overflow.
+pub const MAX_NESTING_DEPTH: usize = 256;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ParseErrorKind {
+    UnexpectedToken,         // A specific token was expected but another one was found
+    NoPrefixParseFn,         // The token cannot start an expression
+    InvalidLiteral,          // The token's lexeme is not a valid value for its literal type
+    InvalidEscape,           // A string literal contains an unknown escape sequence
+    LiteralOverflow,         // A numeric literal is too large for its type
+    InvalidAssignmentTarget, // The left side of `=` or `+=` etc. isn't a variable or element
+    NestingTooDeep,          // Expressions or blocks are nested more than `MAX_NESTING_DEPTH` deep
+    Lex(LexErrorKind),       // The lexer couldn't turn the source into a valid token
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub expected: Option<TokenType>, // The token the parser wanted, if it wanted a specific one
+    pub found: TokenType,            // The token the parser got instead
+    pub lexeme: String,              // The source text of the offending token
+    pub span: Span,                  // Where the offending token is in the source
+}
+
+impl ParseError {
+    pub fn new(kind: ParseErrorKind, expected: Option<TokenType>, found: &Token) -> Self {
+        Self {
+            kind,
+            expected,
+            found: found.token_type,
+            lexeme: found.lexeme.clone(),
+            span: found.span,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.kind, self.expected) {
+            (ParseErrorKind::UnexpectedToken, Some(expected)) => write!(
+                f,
+                "Expected next token to be {:?}, got {:?} instead",
+                expected, self.found
+            ),
+            (ParseErrorKind::UnexpectedToken, None) => {
+                write!(f, "Unexpected token: {:?}", self.found)
+            }
+            (ParseErrorKind::NoPrefixParseFn, _) => {
+                write!(f, "No prefix parse function for {:?} found", self.found)
+            }
+            (ParseErrorKind::InvalidLiteral, _) => {
+                write!(f, "Invalid {:?} literal '{}'", self.found, self.lexeme)
+            }
+            (ParseErrorKind::LiteralOverflow, _) => {
+                write!(
+                    f,
+                    "{:?} literal '{}' is out of range",
+                    self.found, self.lexeme
+                )
+            }
+            (ParseErrorKind::InvalidEscape, _) => {
+                write!(f, "Invalid escape sequence in '{}'", self.lexeme)
+            }
+            (ParseErrorKind::InvalidAssignmentTarget, _) => {
+                write!(f, "Invalid left-hand side of assignment")
+            }
+            (ParseErrorKind::NestingTooDeep, _) => {
+                write!(f, "Nested more than {} levels deep", MAX_NESTING_DEPTH)
+            }
+            (ParseErrorKind::Lex(kind), _) => {
+                LexError::new(kind, self.lexeme.clone(), self.span).fmt(f)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+type PrefixParseFn<'a> = fn(&mut Parser<'a>) -> Option<Expression>;
+type InfixParseFn<'a> = fn(&mut Parser<'a>, Expression) -> Option<Expression>;
+
 #[derive(Debug)]
 pub struct Parser<'a> {
     lexer: Lexer<'a>,
-    errors: Vec<String>,
+    errors: Vec<ParseError>,
     current_token: Option<Token>,
     peek_token: Option<Token>,
-    prefix_parse_fns: HashMap<TokenType, fn(&mut Parser<'a>) -> Option<Expression>>,
-    infix_parse_fns: HashMap<TokenType, fn(&mut Parser<'a>, Expression) -> Option<Expression>>,
+    prefix_parse_fns: HashMap<TokenType, PrefixParseFn<'a>>,
+    infix_parse_fns: HashMap<TokenType, InfixParseFn<'a>>,
+    block_depth: usize,   // How many blocks the current token is inside
+    nesting_depth: usize, // How many expressions and blocks are being parsed inside each other
+    next_id: u32,         // The id the next finished node gets
+    origin: Option<Span>, // Where the source starts in the file, when it's an interpolation
 }
 
 impl<'a> Parser<'a> {
     pub fn new(lexer: Lexer<'a>) -> Self {
+        Self::_new_at(lexer, None)
+    }
+
+    /// Creates a parser whose token spans are moved to start at `origin`, if it's given.
+    fn _new_at(lexer: Lexer<'a>, origin: Option<Span>) -> Self {
         let mut parser = Parser {
             lexer,
             errors: Vec::new(),
@@ -42,11 +165,24 @@ impl<'a> Parser<'a> {
             peek_token: None,
             prefix_parse_fns: HashMap::new(),
             infix_parse_fns: HashMap::new(),
+            block_depth: 0,
+            nesting_depth: 0,
+            next_id: 0,
+            origin,
         };
 
+        parser._register_prefix(TokenType::Identifier, Parser::_parse_identifier);
         parser._register_prefix(TokenType::Integer, Parser::_parse_int_literal);
         parser._register_prefix(TokenType::Float, Parser::_parse_float_literal);
+        parser._register_prefix(TokenType::String, Parser::_parse_string_literal);
+        parser._register_prefix(TokenType::True, Parser::_parse_boolean_literal);
+        parser._register_prefix(TokenType::False, Parser::_parse_boolean_literal);
         parser._register_prefix(TokenType::LeftParen, Parser::_parse_grouped_expression);
+        parser._register_prefix(TokenType::Minus, Parser::_parse_prefix_expression);
+        parser._register_prefix(TokenType::Bang, Parser::_parse_prefix_expression);
+        parser._register_prefix(TokenType::If, Parser::_parse_if_expression);
+        parser._register_prefix(TokenType::LeftBracket, Parser::_parse_array_literal);
+        parser._register_prefix(TokenType::LeftBrace, Parser::_parse_map_literal);
 
         parser._register_infix(TokenType::Plus, Parser::_parse_infix_expression);
         parser._register_infix(TokenType::Minus, Parser::_parse_infix_expression);
@@ -54,6 +190,23 @@ impl<'a> Parser<'a> {
         parser._register_infix(TokenType::Asterisk, Parser::_parse_infix_expression);
         parser._register_infix(TokenType::Pow, Parser::_parse_infix_expression);
         parser._register_infix(TokenType::Modulus, Parser::_parse_infix_expression);
+        parser._register_infix(TokenType::Equal, Parser::_parse_infix_expression);
+        parser._register_infix(TokenType::NotEqual, Parser::_parse_infix_expression);
+        parser._register_infix(TokenType::LessThan, Parser::_parse_infix_expression);
+        parser._register_infix(TokenType::GreaterThan, Parser::_parse_infix_expression);
+        parser._register_infix(TokenType::LessEqual, Parser::_parse_infix_expression);
+        parser._register_infix(TokenType::GreaterEqual, Parser::_parse_infix_expression);
+        parser._register_infix(TokenType::And, Parser::_parse_infix_expression);
+        parser._register_infix(TokenType::Or, Parser::_parse_infix_expression);
+        parser._register_infix(TokenType::LeftParen, Parser::_parse_call_expression);
+        parser._register_infix(TokenType::LeftBracket, Parser::_parse_index_expression);
+        parser._register_infix(TokenType::Assign, Parser::_parse_assign_expression);
+        parser._register_infix(TokenType::PlusAssign, Parser::_parse_assign_expression);
+        parser._register_infix(TokenType::MinusAssign, Parser::_parse_assign_expression);
+        parser._register_infix(TokenType::AsteriskAssign, Parser::_parse_assign_expression);
+        parser._register_infix(TokenType::SlashAssign, Parser::_parse_assign_expression);
+        parser._register_infix(TokenType::PowAssign, Parser::_parse_assign_expression);
+        parser._register_infix(TokenType::ModulusAssign, Parser::_parse_assign_expression);
 
         parser._next_token();
         parser._next_token();
@@ -61,134 +214,987 @@ impl<'a> Parser<'a> {
         parser
     }
 
+    /// Parses the whole token stream. Statements that fail to parse are reported in
+    /// `errors()` and skipped, so the returned program holds every statement that did parse.
     pub fn parse_program(&mut self) -> Program {
-        let mut program = Program::new();
+        let start = self
+            .current_token
+            .as_ref()
+            .map_or(Span::new(1, 0, 0, 0), |token| token.span);
+        let mut statements = Vec::new();
 
         while let Some(ref current_token) = self.current_token {
             if current_token.token_type == TokenType::Eof {
                 break;
             }
 
-            if let Some(stmt) = self._parse_statement() {
-                program.add_statement(Node::Statement(stmt));
+            match self._parse_statement() {
+                Some(stmt) => statements.push(stmt),
+                None => self._synchronize(),
             }
 
             self._next_token();
         }
 
-        program
+        let span = self._span_from(start);
+        Program::new(self._next_id(), span, statements)
+    }
+
+    pub fn errors(&self) -> &[ParseError] {
+        &self.errors
     }
 
     fn _parse_statement(&mut self) -> Option<Statement> {
-        match self.current_token {
-            Some(ref token) => match token.token_type {
-                TokenType::Identifier
-                | TokenType::Integer
-                | TokenType::Float
-                | TokenType::LeftParen => Some(Statement::ExpressionStatement(
-                    self._parse_expression_statement(),
-                )),
-                _ => {
-                    self.errors
-                        .push(format!("Unexpected token: {:?}", token.token_type));
-                    None
+        match self.current_token.as_ref()?.token_type {
+            TokenType::Make => Some(Statement::LetStatement(self._parse_let_statement()?)),
+            TokenType::Deal => Some(Statement::FunctionStatement(
+                self._parse_function_statement()?,
+            )),
+            TokenType::Return => Some(Statement::ReturnStatement(self._parse_return_statement()?)),
+            TokenType::LeftBrace => Some(Statement::BlockStatement(self._parse_block_statement()?)),
+            TokenType::If => Some(Statement::ExpressionStatement(self._parse_if_statement()?)),
+            TokenType::While => Some(Statement::WhileStatement(
+                self._parse_while_statement(None)?,
+            )),
+            TokenType::For => Some(Statement::ForStatement(self._parse_for_statement(None)?)),
+            TokenType::Break => Some(Statement::BreakStatement(self._parse_break_statement()?)),
+            TokenType::Continue => Some(Statement::ContinueStatement(
+                self._parse_continue_statement()?,
+            )),
+            TokenType::Identifier if self._peek_token_is(&TokenType::Colon) => {
+                self._parse_labeled_statement()
+            }
+            _ => Some(Statement::ExpressionStatement(
+                self._parse_expression_statement()?,
+            )),
+        }
+    }
+
+    fn _parse_let_statement(&mut self) -> Option<LetStatement> {
+        let start = self._current_span()?;
+
+        if !self._expect_peek(TokenType::Identifier) {
+            return None;
+        }
+
+        let name = self._current_identifier()?;
+
+        if !self._expect_peek(TokenType::Assign) {
+            return None;
+        }
+
+        self._next_token();
+        let value = self._parse_expression(PrecedenceType::Lowest)?;
+
+        if self._peek_token_is(&TokenType::SemiColon) {
+            self._next_token();
+        }
+
+        let span = self._span_from(start);
+        Some(LetStatement::new(self._next_id(), span, name, value))
+    }
+
+    fn _parse_function_statement(&mut self) -> Option<FunctionStatement> {
+        let start = self._current_span()?;
+
+        if !self._expect_peek(TokenType::Identifier) {
+            return None;
+        }
+
+        let name = self._current_identifier()?;
+
+        if !self._expect_peek(TokenType::LeftParen) {
+            return None;
+        }
+
+        let parameters = self._parse_function_parameters()?;
+
+        if !self._expect_peek(TokenType::LeftBrace) {
+            return None;
+        }
+
+        let body = self._parse_block_statement()?;
+
+        let span = self._span_from(start);
+        Some(FunctionStatement::new(
+            self._next_id(),
+            span,
+            name,
+            parameters,
+            body,
+        ))
+    }
+
+    /// Parses `a, b, c)` after the opening `(` of a `deal`, leaving the `)` as the current token.
+    fn _parse_function_parameters(&mut self) -> Option<Vec<Identifier>> {
+        let mut parameters = Vec::new();
+
+        if self._peek_token_is(&TokenType::RightParen) {
+            self._next_token();
+            return Some(parameters);
+        }
+
+        loop {
+            if !self._expect_peek(TokenType::Identifier) {
+                return None;
+            }
+            parameters.push(self._current_identifier()?);
+
+            if !self._peek_token_is(&TokenType::Comma) {
+                break;
+            }
+            self._next_token();
+        }
+
+        if !self._expect_peek(TokenType::RightParen) {
+            return None;
+        }
+
+        Some(parameters)
+    }
+
+    /// Parses the statements between `{` and `}`, leaving the `}` as the current token.
+    /// Broken statements are reported and skipped the same way `parse_program` does.
+    fn _parse_block_statement(&mut self) -> Option<BlockStatement> {
+        self.block_depth += 1;
+        let block = self._nested(Parser::_parse_block_contents);
+        self.block_depth -= 1;
+        block
+    }
+
+    fn _parse_block_contents(&mut self) -> Option<BlockStatement> {
+        let start = self._current_span()?;
+        let mut statements = Vec::new();
+        self._next_token();
+
+        loop {
+            match self.current_token.as_ref()?.token_type {
+                TokenType::RightBrace => break,
+                TokenType::Eof => {
+                    self._current_token_error_expecting(TokenType::RightBrace);
+                    return None;
+                }
+                _ => {}
+            }
+
+            match self._parse_statement() {
+                Some(stmt) => statements.push(stmt),
+                None => {
+                    self._synchronize();
+                    // The broken statement ran into the block's closing brace
+                    if self._current_token_is(&TokenType::RightBrace) {
+                        break;
+                    }
                 }
-            },
-            None => None,
+            }
+
+            self._next_token();
         }
+
+        let span = self._span_from(start);
+        Some(BlockStatement::new(self._next_id(), span, statements))
     }
 
-    fn _parse_expression_statement(&mut self) -> ExpressionStatement {
-        let expr = self._parse_expression(PrecedenceType::Lowest);
+    fn _parse_return_statement(&mut self) -> Option<ReturnStatement> {
+        let start = self._current_span()?;
+
+        // A bare `return` hands back null
+        let value = if self._peek_token_is(&TokenType::SemiColon)
+            || self._peek_token_is(&TokenType::RightBrace)
+        {
+            None
+        } else {
+            self._next_token();
+            Some(self._parse_expression(PrecedenceType::Lowest)?)
+        };
 
         if self._peek_token_is(&TokenType::SemiColon) {
             self._next_token();
         }
 
-        ExpressionStatement::new(Node::Expression(expr))
+        let span = self._span_from(start);
+        Some(ReturnStatement::new(self._next_id(), span, value))
+    }
+
+    /// An `if` at the start of a statement ends at its closing `}`, so `if a { b } -c` is
+    /// two statements rather than a subtraction.
+    fn _parse_if_statement(&mut self) -> Option<ExpressionStatement> {
+        let start = self._current_span()?;
+        let expr = self._parse_if_expression()?;
+
+        if self._peek_token_is(&TokenType::SemiColon) {
+            self._next_token();
+        }
+
+        let span = self._span_from(start);
+        Some(ExpressionStatement::new(self._next_id(), span, expr))
+    }
+
+    /// Parses `label: while ...` or `label: for ...`.
+    fn _parse_labeled_statement(&mut self) -> Option<Statement> {
+        let label = self._current_identifier()?;
+        self._next_token();
+
+        if self._peek_token_is(&TokenType::For) {
+            self._next_token();
+            return Some(Statement::ForStatement(
+                self._parse_for_statement(Some(label))?,
+            ));
+        }
+
+        if !self._expect_peek(TokenType::While) {
+            return None;
+        }
+
+        Some(Statement::WhileStatement(
+            self._parse_while_statement(Some(label))?,
+        ))
+    }
+
+    fn _parse_while_statement(&mut self, label: Option<Identifier>) -> Option<WhileStatement> {
+        let start = self._loop_start(&label)?;
+
+        self._next_token();
+        let condition = self._parse_expression(PrecedenceType::Lowest)?;
+
+        if !self._expect_peek(TokenType::LeftBrace) {
+            return None;
+        }
+
+        let body = self._parse_block_statement()?;
+
+        let span = self._span_from(start);
+        Some(WhileStatement::new(
+            self._next_id(),
+            span,
+            label,
+            condition,
+            body,
+        ))
     }
 
-    fn _parse_expression(&mut self, precedence: PrecedenceType) -> Expression {
+    fn _parse_for_statement(&mut self, label: Option<Identifier>) -> Option<ForStatement> {
+        let start = self._loop_start(&label)?;
+
+        if !self._expect_peek(TokenType::Identifier) {
+            return None;
+        }
+
+        let variable = self._current_identifier()?;
+
+        if !self._expect_peek(TokenType::In) {
+            return None;
+        }
+
+        self._next_token();
+        let range_start = self._parse_expression(PrecedenceType::Lowest)?;
+
+        if !self._expect_peek(TokenType::DotDot) {
+            return None;
+        }
+
+        self._next_token();
+        let range_end = self._parse_expression(PrecedenceType::Lowest)?;
+
+        if !self._expect_peek(TokenType::LeftBrace) {
+            return None;
+        }
+
+        let body = self._parse_block_statement()?;
+
+        let span = self._span_from(start);
+        Some(ForStatement::new(
+            self._next_id(),
+            span,
+            label,
+            variable,
+            range_start,
+            range_end,
+            body,
+        ))
+    }
+
+    /// Where a loop statement starts: at its label if it has one, or else at its keyword.
+    fn _loop_start(&self, label: &Option<Identifier>) -> Option<Span> {
+        match label {
+            Some(label) => Some(label.span),
+            None => self._current_span(),
+        }
+    }
+
+    fn _parse_break_statement(&mut self) -> Option<BreakStatement> {
+        let start = self._current_span()?;
+        let label = self._parse_jump_label();
+
+        let span = self._span_from(start);
+        Some(BreakStatement::new(self._next_id(), span, label))
+    }
+
+    fn _parse_continue_statement(&mut self) -> Option<ContinueStatement> {
+        let start = self._current_span()?;
+        let label = self._parse_jump_label();
+
+        let span = self._span_from(start);
+        Some(ContinueStatement::new(self._next_id(), span, label))
+    }
+
+    /// Parses the optional label after `break` or `continue`, and the optional `;`.
+    fn _parse_jump_label(&mut self) -> Option<Identifier> {
+        let mut label = None;
+
+        if self._peek_token_is(&TokenType::Identifier) {
+            self._next_token();
+            label = self._current_identifier();
+        }
+
+        if self._peek_token_is(&TokenType::SemiColon) {
+            self._next_token();
+        }
+
+        label
+    }
+
+    fn _parse_expression_statement(&mut self) -> Option<ExpressionStatement> {
+        let start = self._current_span()?;
+        let expr = self._parse_expression(PrecedenceType::Lowest)?;
+
+        if self._peek_token_is(&TokenType::SemiColon) {
+            self._next_token();
+        }
+
+        let span = self._span_from(start);
+        Some(ExpressionStatement::new(self._next_id(), span, expr))
+    }
+
+    fn _parse_expression(&mut self, precedence: PrecedenceType) -> Option<Expression> {
+        self._nested(|parser| parser._parse_expression_contents(precedence))
+    }
+
+    /// Runs `f` one level deeper, or returns `None` if that would pass `MAX_NESTING_DEPTH`.
+    /// Only the first token that goes too deep is reported, since recovering inside the
+    /// deepest block would otherwise report every statement after it as well.
+    ///
+    /// The stack is grown as needed too, since a level can take several kilobytes of it in
+    /// a debug build and the parser may be running on a small thread.
+    fn _nested<T>(&mut self, f: impl FnOnce(&mut Self) -> Option<T>) -> Option<T> {
+        if self.nesting_depth >= MAX_NESTING_DEPTH {
+            if !self
+                .errors
+                .iter()
+                .any(|e| e.kind == ParseErrorKind::NestingTooDeep)
+            {
+                self._current_token_error(ParseErrorKind::NestingTooDeep);
+            }
+            return None;
+        }
+
+        self.nesting_depth += 1;
+        let result = stacker::maybe_grow(64 * 1024, 2 * 1024 * 1024, || f(self));
+        self.nesting_depth -= 1;
+        result
+    }
+
+    fn _parse_expression_contents(&mut self, precedence: PrecedenceType) -> Option<Expression> {
         let prefix_fn = {
-            let current_token_type = &self.current_token.as_ref().unwrap().token_type;
+            let current_token_type = &self.current_token.as_ref()?.token_type;
             self.prefix_parse_fns.get(current_token_type).cloned()
         };
 
-        if prefix_fn.is_none() {
-            self._no_prefix_parse_in_error(self.current_token.as_ref().unwrap().token_type);
-            return Expression::IntegerLiteral(IntegerLiteral::new(0));
-        }
+        let Some(prefix_fn) = prefix_fn else {
+            self._no_prefix_parse_in_error();
+            return None;
+        };
 
-        let mut left_expr = prefix_fn.unwrap()(self).unwrap();
+        let mut left_expr = prefix_fn(self)?;
 
-        while !self._peek_token_is(&TokenType::SemiColon) && precedence < self._peek_precedence() {
+        while !self._peek_token_is(&TokenType::SemiColon) && self._peek_binds_tighter(precedence) {
             let infix_fn = {
-                let peek_token_type = &self.peek_token.as_ref().unwrap().token_type;
+                let peek_token_type = &self.peek_token.as_ref()?.token_type;
                 self.infix_parse_fns.get(peek_token_type).cloned()
             };
 
-            if infix_fn.is_none() {
-                return left_expr;
-            }
+            let Some(infix_fn) = infix_fn else {
+                return Some(left_expr);
+            };
 
             self._next_token();
-
-            left_expr = infix_fn.unwrap()(self, left_expr).unwrap();
+            left_expr = infix_fn(self, left_expr)?;
         }
 
-        left_expr
+        Some(left_expr)
+    }
+
+    fn _parse_identifier(&mut self) -> Option<Expression> {
+        Some(Expression::Identifier(self._current_identifier()?))
     }
 
     fn _parse_int_literal(&mut self) -> Option<Expression> {
-        let value = self
-            .current_token
-            .as_ref()
-            .unwrap()
-            .lexeme
-            .parse::<i64>()
-            .unwrap();
-        Some(Expression::IntegerLiteral(IntegerLiteral::new(value)))
+        let span = self._current_span()?;
+
+        match self._int_literal_value(false)? {
+            Ok(value) => Some(Expression::IntegerLiteral(IntegerLiteral::new(
+                self._next_id(),
+                span,
+                value,
+            ))),
+            Err(kind) => {
+                self._current_token_error(kind);
+                None
+            }
+        }
+    }
+
+    /// The value of the current integer token, or of the token with a `-` in front of it
+    /// if `negated` is set.
+    fn _int_literal_value(&self, negated: bool) -> Option<Result<i64, ParseErrorKind>> {
+        let lexeme = self.current_token.as_ref()?.lexeme.replace('_', "");
+        let digits = lexeme.strip_suffix("i64").unwrap_or(&lexeme);
+
+        let (digits, radix) = match digits.get(..2) {
+            Some("0x" | "0X") => (&digits[2..], 16),
+            Some("0o" | "0O") => (&digits[2..], 8),
+            Some("0b" | "0B") => (&digits[2..], 2),
+            _ => (digits, 10),
+        };
+
+        let result = match negated {
+            true => i64::from_str_radix(&format!("-{}", digits), radix),
+            false => i64::from_str_radix(digits, radix),
+        };
+
+        Some(result.map_err(|error| match error.kind() {
+            IntErrorKind::PosOverflow | IntErrorKind::NegOverflow => {
+                ParseErrorKind::LiteralOverflow
+            }
+            _ => ParseErrorKind::InvalidLiteral,
+        }))
     }
 
     fn _parse_float_literal(&mut self) -> Option<Expression> {
-        let value = self
-            .current_token
-            .as_ref()
-            .unwrap()
-            .lexeme
-            .parse::<f64>()
-            .unwrap();
-        Some(Expression::FloatLiteral(FloatLiteral::new(value)))
+        let span = self._current_span()?;
+        let lexeme = self.current_token.as_ref()?.lexeme.replace('_', "");
+        let digits = lexeme.strip_suffix("f64").unwrap_or(&lexeme);
+
+        match digits.parse::<f64>() {
+            Ok(value) if value.is_finite() => Some(Expression::FloatLiteral(FloatLiteral::new(
+                self._next_id(),
+                span,
+                value,
+            ))),
+            Ok(_) => {
+                self._current_token_error(ParseErrorKind::LiteralOverflow);
+                None
+            }
+            Err(_) => {
+                self._current_token_error(ParseErrorKind::InvalidLiteral);
+                None
+            }
+        }
+    }
+
+    /// Decodes the escapes in a string literal and parses every `{expr}` in it as an
+    /// expression. Literal braces are written as `\{` and `\}`.
+    fn _parse_string_literal(&mut self) -> Option<Expression> {
+        let span = self._current_span()?;
+        let lexeme = self.current_token.as_ref()?.lexeme.clone();
+        let raw = &lexeme[1..lexeme.len() - 1];
+
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut literal_start = 0; // Where the text segment being decoded starts in `raw`
+        let mut index = 0;
+
+        while let Some(ch) = raw[index..].chars().next() {
+            match ch {
+                '\\' => {
+                    let Some((decoded, len)) = Self::_decode_escape(&raw[index + 1..]) else {
+                        self._current_token_error(ParseErrorKind::InvalidEscape);
+                        return None;
+                    };
+                    literal.push(decoded);
+                    index += 1 + len;
+                }
+                '{' => {
+                    let Some(end) = Self::_find_interpolation_end(raw, index + 1) else {
+                        self._current_token_error(ParseErrorKind::InvalidLiteral);
+                        return None;
+                    };
+
+                    if !literal.is_empty() {
+                        let segment_span =
+                            Self::_span_within(span, &lexeme, literal_start + 1, index + 1);
+                        let segment = StringLiteral::new(
+                            self._next_id(),
+                            segment_span,
+                            std::mem::take(&mut literal),
+                        );
+                        parts.push(InterpolationPart::StringLiteral(segment));
+                    }
+
+                    let origin = Self::_span_within(span, &lexeme, index + 2, end + 1);
+                    let expr = self._parse_interpolation(&raw[index + 1..end], origin)?;
+                    parts.push(InterpolationPart::Expression(expr));
+                    index = end + 1;
+                    literal_start = index;
+                }
+                '}' => {
+                    self._current_token_error(ParseErrorKind::InvalidLiteral);
+                    return None;
+                }
+                _ => {
+                    literal.push(ch);
+                    index += ch.len_utf8();
+                }
+            }
+        }
+
+        if parts.is_empty() {
+            return Some(Expression::StringLiteral(StringLiteral::new(
+                self._next_id(),
+                span,
+                literal,
+            )));
+        }
+
+        if !literal.is_empty() {
+            let segment_span = Self::_span_within(span, &lexeme, literal_start + 1, raw.len() + 1);
+            parts.push(InterpolationPart::StringLiteral(StringLiteral::new(
+                self._next_id(),
+                segment_span,
+                literal,
+            )));
+        }
+
+        Some(Expression::StringInterpolation(StringInterpolation::new(
+            self._next_id(),
+            span,
+            parts,
+        )))
     }
 
+    /// The span of `text[start..end]`, where `text` is the source of the token at `span`.
+    fn _span_within(span: Span, text: &str, start: usize, end: usize) -> Span {
+        let before = &text[..start];
+        let line = span.line + before.matches('\n').count();
+        let column = match before.rfind('\n') {
+            Some(newline) => before[newline + 1..].chars().count(),
+            None => span.column + before.chars().count(),
+        };
+
+        Span::new(line, column, span.start + start, span.start + end)
+    }
+
+    /// Decodes the escape sequence at the start of `rest` (just after the backslash),
+    /// returning the character and how many bytes of `rest` it used.
+    fn _decode_escape(rest: &str) -> Option<(char, usize)> {
+        let decoded = match rest.chars().next()? {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            '0' => '\0',
+            '\\' => '\\',
+            '"' => '"',
+            '{' => '{',
+            '}' => '}',
+            'u' => {
+                let digits = rest.strip_prefix("u{")?.split('}').next()?;
+                // Checked by hand, since `from_str_radix` would also take a leading `+`
+                if digits.is_empty()
+                    || digits.len() > 6
+                    || !digits.chars().all(|ch| ch.is_ascii_hexdigit())
+                {
+                    return None;
+                }
+                let decoded = char::from_u32(u32::from_str_radix(digits, 16).ok()?)?;
+                return Some((decoded, digits.len() + 3));
+            }
+            _ => return None,
+        };
+
+        Some((decoded, 1))
+    }
+
+    /// Finds the `}` closing the interpolation whose contents start at `start`, skipping
+    /// over nested braces and string literals the same way the lexer does.
+    fn _find_interpolation_end(raw: &str, start: usize) -> Option<usize> {
+        let mut depth = 0;
+        let mut in_string = false;
+        let mut chars = raw[start..].char_indices();
+
+        while let Some((offset, ch)) = chars.next() {
+            match ch {
+                '\\' => {
+                    chars.next();
+                }
+                '"' => in_string = !in_string,
+                '{' if !in_string => depth += 1,
+                '}' if !in_string && depth == 0 => return Some(start + offset),
+                '}' if !in_string => depth -= 1,
+                _ => {}
+            }
+        }
+
+        None
+    }
+
+    /// Parses the source of one `{...}`, which starts at `origin` in the file, as a single
+    /// expression. Errors inside it are reported at the enclosing string literal.
+    fn _parse_interpolation(&mut self, source: &str, origin: Span) -> Option<Expression> {
+        let mut parser = Parser::_new_at(Lexer::new(source), Some(origin));
+        parser.next_id = self.next_id;
+        parser.nesting_depth = self.nesting_depth;
+
+        let expr = parser._parse_expression(PrecedenceType::Lowest);
+
+        if expr.is_some() && !parser._peek_token_is(&TokenType::Eof) {
+            parser._expect_peek(TokenType::Eof);
+        }
+
+        self.next_id = parser.next_id;
+
+        if parser.errors.is_empty() {
+            return expr;
+        }
+
+        let span = self.current_token.as_ref()?.span;
+        for mut error in parser.errors {
+            error.span = span;
+            self.errors.push(error);
+        }
+
+        None
+    }
+
+    fn _parse_boolean_literal(&mut self) -> Option<Expression> {
+        let span = self._current_span()?;
+        let value = self.current_token.as_ref()?.token_type == TokenType::True;
+        Some(Expression::BooleanLiteral(BooleanLiteral::new(
+            self._next_id(),
+            span,
+            value,
+        )))
+    }
+
+    /// Parses `(expr)` into `expr`, widening its span to take in the parens, so the span of
+    /// an expression built around it like `(1 + 2) * 3` starts at the `(`.
     fn _parse_grouped_expression(&mut self) -> Option<Expression> {
+        let start = self._current_span()?;
         self._next_token();
-        let expr = self._parse_expression(PrecedenceType::Lowest);
+        let mut expr = self._parse_expression(PrecedenceType::Lowest)?;
 
         if !self._expect_peek(TokenType::RightParen) {
             return None;
         }
 
+        *expr.span_mut() = self._span_from(start);
         Some(expr)
     }
 
+    fn _parse_prefix_expression(&mut self) -> Option<Expression> {
+        let start = self._current_span()?;
+        let operator = UnaryOp::from_token_type(self.current_token.as_ref()?.token_type)?;
+        self._next_token();
+
+        // `i64::MIN` is one further from zero than `i64::MAX`, so its digits alone don't fit.
+        // Like rustc, read a literal negated directly as one negative literal in that case.
+        if operator == UnaryOp::Negate
+            && self._current_token_is(&TokenType::Integer)
+            && !self._peek_binds_tighter(PrecedenceType::Prefix)
+            && self._int_literal_value(true) == Some(Ok(i64::MIN))
+        {
+            let span = self._span_from(start);
+            return Some(Expression::IntegerLiteral(IntegerLiteral::new(
+                self._next_id(),
+                span,
+                i64::MIN,
+            )));
+        }
+
+        let right = self._parse_expression(PrecedenceType::Prefix)?;
+
+        let span = self._span_from(start);
+        Some(Expression::PrefixExpression(PrefixExpression::new(
+            self._next_id(),
+            span,
+            operator,
+            right,
+        )))
+    }
+
     fn _parse_infix_expression(&mut self, left: Expression) -> Option<Expression> {
-        let operator = self.current_token.as_ref().unwrap().lexeme.clone();
+        let operator = BinaryOp::from_token_type(self.current_token.as_ref()?.token_type)?;
         let precedence = self._current_precedence();
         self._next_token();
 
-        let right = self._parse_expression(precedence);
+        let right = self._parse_expression(precedence)?;
 
+        let span = self._span_from(left.span());
         Some(Expression::InfixExpression(InfixExpression::new(
-            Node::Expression(left),
+            self._next_id(),
+            span,
+            left,
+            operator,
+            right,
+        )))
+    }
+
+    fn _parse_if_expression(&mut self) -> Option<Expression> {
+        Some(Expression::IfExpression(self._parse_if()?))
+    }
+
+    /// Parses `if condition { ... }` and any `else` or `else if` chain after it.
+    fn _parse_if(&mut self) -> Option<IfExpression> {
+        let start = self._current_span()?;
+
+        self._next_token();
+        let condition = self._parse_expression(PrecedenceType::Lowest)?;
+
+        if !self._expect_peek(TokenType::LeftBrace) {
+            return None;
+        }
+
+        let consequence = self._parse_block_statement()?;
+
+        let alternative = if self._peek_token_is(&TokenType::Else) {
+            self._next_token();
+
+            if self._peek_token_is(&TokenType::If) {
+                self._next_token();
+                Some(ElseBranch::IfExpression(Box::new(self._parse_if()?)))
+            } else {
+                if !self._expect_peek(TokenType::LeftBrace) {
+                    return None;
+                }
+                Some(ElseBranch::BlockStatement(self._parse_block_statement()?))
+            }
+        } else {
+            None
+        };
+
+        let span = self._span_from(start);
+        Some(IfExpression::new(
+            self._next_id(),
+            span,
+            condition,
+            consequence,
+            alternative,
+        ))
+    }
+
+    fn _parse_call_expression(&mut self, function: Expression) -> Option<Expression> {
+        let arguments = self._parse_expression_list(TokenType::RightParen)?;
+
+        let span = self._span_from(function.span());
+        Some(Expression::CallExpression(CallExpression::new(
+            self._next_id(),
+            span,
+            function,
+            arguments,
+        )))
+    }
+
+    fn _parse_array_literal(&mut self) -> Option<Expression> {
+        let start = self._current_span()?;
+        let elements = self._parse_expression_list(TokenType::RightBracket)?;
+
+        let span = self._span_from(start);
+        Some(Expression::ArrayLiteral(ArrayLiteral::new(
+            self._next_id(),
+            span,
+            elements,
+        )))
+    }
+
+    /// Parses `{ key: value, ... }`. A `{` only starts a map where an expression is
+    /// expected; at the start of a statement it opens a block.
+    fn _parse_map_literal(&mut self) -> Option<Expression> {
+        let start = self._current_span()?;
+        let mut pairs = Vec::new();
+
+        while !self._peek_token_is(&TokenType::RightBrace) {
+            self._next_token();
+            let key = self._parse_expression(PrecedenceType::Lowest)?;
+
+            if !self._expect_peek(TokenType::Colon) {
+                return None;
+            }
+
+            self._next_token();
+            let value = self._parse_expression(PrecedenceType::Lowest)?;
+            pairs.push((key, value));
+
+            if !self._peek_token_is(&TokenType::RightBrace) && !self._expect_peek(TokenType::Comma)
+            {
+                return None;
+            }
+        }
+
+        self._next_token();
+
+        let span = self._span_from(start);
+        Some(Expression::MapLiteral(MapLiteral::new(
+            self._next_id(),
+            span,
+            pairs,
+        )))
+    }
+
+    fn _parse_index_expression(&mut self, left: Expression) -> Option<Expression> {
+        self._next_token();
+        let index = self._parse_expression(PrecedenceType::Lowest)?;
+
+        if !self._expect_peek(TokenType::RightBracket) {
+            return None;
+        }
+
+        let span = self._span_from(left.span());
+        Some(Expression::IndexExpression(IndexExpression::new(
+            self._next_id(),
+            span,
+            left,
+            index,
+        )))
+    }
+
+    /// Parses `target = value` or a compound assignment like `target += value`. Only
+    /// variables and array or map elements can be assigned to.
+    fn _parse_assign_expression(&mut self, target: Expression) -> Option<Expression> {
+        let target = match target {
+            Expression::Identifier(ident) => AssignTarget::Identifier(ident),
+            Expression::IndexExpression(index_expr) => AssignTarget::IndexExpression(index_expr),
+            _ => {
+                self._current_token_error(ParseErrorKind::InvalidAssignmentTarget);
+                return None;
+            }
+        };
+
+        let operator = AssignOp::from_token_type(self.current_token.as_ref()?.token_type);
+        let precedence = self._current_precedence();
+        self._next_token();
+
+        let value = self._parse_expression(precedence)?;
+
+        let span = self._span_from(target.span());
+        Some(Expression::AssignExpression(AssignExpression::new(
+            self._next_id(),
+            span,
+            target,
             operator,
-            Node::Expression(right),
+            value,
         )))
     }
 
+    /// Parses comma separated expressions up to and including `end`, which becomes the
+    /// current token.
+    fn _parse_expression_list(&mut self, end: TokenType) -> Option<Vec<Expression>> {
+        let mut list = Vec::new();
+
+        if self._peek_token_is(&end) {
+            self._next_token();
+            return Some(list);
+        }
+
+        self._next_token();
+        list.push(self._parse_expression(PrecedenceType::Lowest)?);
+
+        while self._peek_token_is(&TokenType::Comma) {
+            self._next_token();
+            self._next_token();
+            list.push(self._parse_expression(PrecedenceType::Lowest)?);
+        }
+
+        if !self._expect_peek(end) {
+            return None;
+        }
+
+        Some(list)
+    }
+
+    /// Skips the rest of a broken statement so parsing can resume at the next one, which
+    /// starts either after a `;`, at a statement keyword or, inside a block, at the `}`
+    /// closing it.
+    fn _synchronize(&mut self) {
+        let in_block = self.block_depth > 0;
+
+        while let Some(ref current_token) = self.current_token {
+            let next_statement_starts = match self.peek_token.as_ref().map(|t| t.token_type) {
+                Some(
+                    TokenType::Make
+                    | TokenType::Deal
+                    | TokenType::Return
+                    | TokenType::If
+                    | TokenType::While
+                    | TokenType::For
+                    | TokenType::Break
+                    | TokenType::Continue,
+                ) => true,
+                Some(TokenType::RightBrace) => in_block,
+                _ => false,
+            };
+
+            let statement_ends = match current_token.token_type {
+                TokenType::SemiColon | TokenType::Eof => true,
+                TokenType::RightBrace => in_block,
+                _ => false,
+            };
+
+            if next_statement_starts || statement_ends {
+                break;
+            }
+
+            self._next_token();
+        }
+    }
+
     fn _next_token(&mut self) {
         self.current_token = self.peek_token.take();
-        self.peek_token = Some(self.lexer.next_token());
+        let mut token = self.lexer.next_token();
+        if let Some(origin) = self.origin {
+            token.span = token.span.relative_to(origin);
+        }
+
+        // Report `Illegal` tokens once, here, rather than at every place they trip up the parser
+        if token.token_type == TokenType::Illegal {
+            if let Some(error) = self.lexer.errors().last() {
+                let error = ParseError::new(ParseErrorKind::Lex(error.kind), None, &token);
+                self.errors.push(error);
+            }
+        }
+
+        self.peek_token = Some(token);
+    }
+
+    fn _next_id(&mut self) -> NodeId {
+        let id = NodeId(self.next_id);
+        self.next_id += 1;
+        id
+    }
+
+    fn _current_span(&self) -> Option<Span> {
+        Some(self.current_token.as_ref()?.span)
+    }
+
+    /// The span from `start` to the end of the current token, which is the last token of
+    /// the node being parsed.
+    fn _span_from(&self, start: Span) -> Span {
+        match self.current_token {
+            Some(ref token) => start.to(token.span),
+            None => start,
+        }
+    }
+
+    fn _current_identifier(&mut self) -> Option<Identifier> {
+        let token = self.current_token.as_ref()?;
+        let (span, name) = (token.span, token.lexeme.clone());
+        Some(Identifier::new(self._next_id(), span, name))
+    }
+
+    fn _current_token_is(&self, token_type: &TokenType) -> bool {
+        if let Some(ref current_token) = self.current_token {
+            &current_token.token_type == token_type
+        } else {
+            false
+        }
     }
 
     fn _peek_token_is(&self, token_type: &TokenType) -> bool {
@@ -211,17 +1217,46 @@ impl<'a> Parser<'a> {
 
     fn _peek_error(&mut self, token_type: &TokenType) {
         if let Some(ref peek_token) = self.peek_token {
-            let error = format!(
-                "Expected next token to be {:?}, got {:?} instead",
-                token_type, peek_token.token_type
+            if peek_token.token_type == TokenType::Illegal {
+                return;
+            }
+
+            let error = ParseError::new(
+                ParseErrorKind::UnexpectedToken,
+                Some(*token_type),
+                peek_token,
+            );
+            self.errors.push(error);
+        }
+    }
+
+    fn _current_token_error(&mut self, kind: ParseErrorKind) {
+        if let Some(ref current_token) = self.current_token {
+            let error = ParseError::new(kind, None, current_token);
+            self.errors.push(error);
+        }
+    }
+
+    fn _current_token_error_expecting(&mut self, token_type: TokenType) {
+        if let Some(ref current_token) = self.current_token {
+            let error = ParseError::new(
+                ParseErrorKind::UnexpectedToken,
+                Some(token_type),
+                current_token,
             );
             self.errors.push(error);
         }
     }
 
-    fn _no_prefix_parse_in_error(&mut self, token_type: TokenType) {
-        let error = format!("No prefix parse function for {:?} found", token_type);
-        self.errors.push(error);
+    fn _no_prefix_parse_in_error(&mut self) {
+        if let Some(ref current_token) = self.current_token {
+            if current_token.token_type == TokenType::Illegal {
+                return;
+            }
+
+            let error = ParseError::new(ParseErrorKind::NoPrefixParseFn, None, current_token);
+            self.errors.push(error);
+        }
     }
 
     fn _current_precedence(&self) -> PrecedenceType {
@@ -238,19 +1273,24 @@ impl<'a> Parser<'a> {
         }
     }
 
-    fn _register_prefix(
-        &mut self,
-        token_type: TokenType,
-        func: fn(&mut Parser<'a>) -> Option<Expression>,
-    ) {
+    /// Whether the peek operator should take the expression parsed so far as its left
+    /// operand. A right-associative operator also does so at its own precedence, which
+    /// makes `2 ^ 3 ^ 2` nest to the right.
+    fn _peek_binds_tighter(&self, precedence: PrecedenceType) -> bool {
+        let peek_precedence = self._peek_precedence();
+        let right_associative = match self.peek_token {
+            Some(ref token) => token.token_type.associativity() == Associativity::Right,
+            None => false,
+        };
+
+        precedence < peek_precedence || (right_associative && precedence == peek_precedence)
+    }
+
+    fn _register_prefix(&mut self, token_type: TokenType, func: PrefixParseFn<'a>) {
         self.prefix_parse_fns.insert(token_type, func);
     }
 
-    fn _register_infix(
-        &mut self,
-        token_type: TokenType,
-        func: fn(&mut Parser<'a>, Expression) -> Option<Expression>,
-    ) {
+    fn _register_infix(&mut self, token_type: TokenType, func: InfixParseFn<'a>) {
         self.infix_parse_fns.insert(token_type, func);
     }
 }
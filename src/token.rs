@@ -1,13 +1,36 @@
-use crate::parser::PrecedenceType;
+use crate::parser::{Associativity, PrecedenceType};
+use serde::{Deserialize, Serialize};
 
 #[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
 pub enum TokenType {
     // Variables
     Identifier,
+    Assign,
+    PlusAssign,
+    MinusAssign,
+    AsteriskAssign,
+    SlashAssign,
+    PowAssign,
+    ModulusAssign,
+
+    // Keywords
+    Make,
+    True,
+    False,
+    Deal,
+    Return,
+    If,
+    Else,
+    While,
+    For,
+    In,
+    Break,
+    Continue,
 
     // Data types
     Integer,
     Float,
+    String,
 
     // Arithmetic operators
     Plus,
@@ -17,10 +40,30 @@ pub enum TokenType {
     Pow,
     Modulus,
 
+    // Comparison operators
+    Equal,
+    NotEqual,
+    LessThan,
+    GreaterThan,
+    LessEqual,
+    GreaterEqual,
+
+    // Logical operators
+    Bang,
+    And,
+    Or,
+
     // Symbols
     SemiColon,
+    Colon,
+    Comma,
+    DotDot,
     LeftParen,
     RightParen,
+    LeftBrace,
+    RightBrace,
+    LeftBracket,
+    RightBracket,
 
     // Errors
     Eof,
@@ -30,29 +73,139 @@ pub enum TokenType {
 impl TokenType {
     pub fn precedence(&self) -> PrecedenceType {
         match self {
+            TokenType::Assign
+            | TokenType::PlusAssign
+            | TokenType::MinusAssign
+            | TokenType::AsteriskAssign
+            | TokenType::SlashAssign
+            | TokenType::PowAssign
+            | TokenType::ModulusAssign => PrecedenceType::Assign,
             TokenType::Plus | TokenType::Minus => PrecedenceType::Sum,
             TokenType::Asterisk | TokenType::Slash | TokenType::Modulus => PrecedenceType::Product,
             TokenType::Pow => PrecedenceType::Power,
+            TokenType::Equal | TokenType::NotEqual => PrecedenceType::Equals,
+            TokenType::LessThan
+            | TokenType::GreaterThan
+            | TokenType::LessEqual
+            | TokenType::GreaterEqual => PrecedenceType::LessGreater,
+            TokenType::And => PrecedenceType::And,
+            TokenType::Or => PrecedenceType::Or,
+            TokenType::LeftParen => PrecedenceType::Call,
+            TokenType::LeftBracket => PrecedenceType::Index,
             _ => PrecedenceType::Lowest,
         }
     }
+
+    pub fn associativity(&self) -> Associativity {
+        match self {
+            TokenType::Pow
+            | TokenType::Assign
+            | TokenType::PlusAssign
+            | TokenType::MinusAssign
+            | TokenType::AsteriskAssign
+            | TokenType::SlashAssign
+            | TokenType::PowAssign
+            | TokenType::ModulusAssign => Associativity::Right,
+            _ => Associativity::Left,
+        }
+    }
+}
+
+/// Maps an identifier to its keyword token type, or `TokenType::Identifier` if it isn't one.
+pub fn lookup_identifier(identifier: &str) -> TokenType {
+    match identifier {
+        "make" => TokenType::Make,
+        "tremendous" => TokenType::True,
+        "fake_news" => TokenType::False,
+        "deal" => TokenType::Deal,
+        "return" => TokenType::Return,
+        "if" => TokenType::If,
+        "else" => TokenType::Else,
+        "while" => TokenType::While,
+        "for" => TokenType::For,
+        "in" => TokenType::In,
+        "break" => TokenType::Break,
+        "continue" => TokenType::Continue,
+        _ => TokenType::Identifier,
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub struct Span {
+    pub line: usize,   // The line number where the span begins (1-based)
+    pub column: usize, // The number of characters before the span on its first line
+    pub start: usize,  // The byte offset of the first character of the span
+    pub end: usize,    // The byte offset one past the last character of the span
+}
+
+impl Span {
+    pub fn new(line: usize, column: usize, start: usize, end: usize) -> Self {
+        Self {
+            line,
+            column,
+            start,
+            end,
+        }
+    }
+
+    /// The span from the start of this one to the end of `other`.
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            end: other.end,
+            ..self
+        }
+    }
+
+    /// Moves a span measured from the start of some inner source, like the text of a
+    /// string interpolation, to where that source starts in the file.
+    pub fn relative_to(self, origin: Span) -> Span {
+        Span {
+            line: origin.line + self.line - 1,
+            column: if self.line == 1 {
+                origin.column + self.column
+            } else {
+                self.column
+            },
+            start: origin.start + self.start,
+            end: origin.start + self.end,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TriviaKind {
+    LineDocComment,  // `/// ...`
+    BlockDocComment, // `/** ... */`
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    pub text: String, // The comment's contents without the comment markers
+    pub span: Span,   // Where the whole comment, markers included, is in the source
+}
+
+impl Trivia {
+    pub fn new(kind: TriviaKind, text: String, span: Span) -> Self {
+        Self { kind, text, span }
+    }
 }
 
 #[derive(Debug)]
 pub struct Token {
     pub token_type: TokenType,
-    pub lexeme: String,  // The string literal encoding of the current token
-    pub line: usize,     // The line number where the token was found
-    pub position: usize, // The position (index) in the line where the token was found
+    pub lexeme: String,      // The string literal encoding of the current token
+    pub span: Span,          // Where the token is in the source
+    pub trivia: Vec<Trivia>, // Doc comments directly before the token, if the lexer keeps them
 }
 
 impl Token {
-    pub fn new(token_type: TokenType, lexeme: String, line: usize, position: usize) -> Self {
+    pub fn new(token_type: TokenType, lexeme: String, span: Span) -> Self {
         Self {
             token_type,
             lexeme,
-            line,
-            position,
+            span,
+            trivia: Vec::new(),
         }
     }
 }
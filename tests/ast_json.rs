@@ -0,0 +1,97 @@
+// the best ASTs, they go out and they come right back
+
+use trump::ast::Program;
+use trump::lexer::Lexer;
+use trump::parser::Parser;
+
+const STACK_SIZE: usize = 64 * 1024 * 1024;
+
+fn parse(source: &str) -> Program {
+    let mut parser = Parser::new(Lexer::new(source));
+    let program = parser.parse_program();
+    assert!(parser.errors().is_empty(), "{:?}", parser.errors());
+    program
+}
+
+fn sum_chain(terms: usize) -> String {
+    format!("{};", vec!["1"; terms].join(" + "))
+}
+
+fn else_if_chain(branches: usize) -> String {
+    let mut source = String::from("make x = 1;\nif x == 0 { 0; }");
+    for i in 1..branches {
+        source += &format!(" else if x == {} {{ {}; }}", i, i);
+    }
+    source + " else { 1; }"
+}
+
+/// Dropping and comparing a deep tree recurses, so give it the stack `main` runs on.
+fn on_large_stack(test: impl FnOnce() + Send + 'static) {
+    std::thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(test)
+        .unwrap()
+        .join()
+        .unwrap();
+}
+
+#[test]
+fn test_files_round_trip() {
+    for entry in std::fs::read_dir("test_files").unwrap() {
+        let source = std::fs::read_to_string(entry.unwrap().path()).unwrap();
+        let mut parser = Parser::new(Lexer::new(&source));
+        let program = parser.parse_program();
+
+        assert_eq!(Program::from_json(&program.to_json()).unwrap(), program);
+    }
+}
+
+#[test]
+fn json_size_grows_linearly_with_depth() {
+    on_large_stack(|| {
+        let small = parse(&sum_chain(2_000)).to_json().len();
+        let large = parse(&sum_chain(8_000)).to_json().len();
+
+        // Four times the terms is about four times the JSON, not sixteen
+        assert!(
+            large < small * 5,
+            "{} bytes for 2,000 terms but {} for 8,000",
+            small,
+            large
+        );
+    });
+}
+
+#[test]
+fn deep_programs_round_trip() {
+    on_large_stack(|| {
+        for source in [sum_chain(16_000), else_if_chain(1_000)] {
+            let program = parse(&source);
+            assert_eq!(Program::from_json(&program.to_json()).unwrap(), program);
+        }
+    });
+}
+
+#[test]
+fn rejects_other_versions() {
+    let error = Program::from_json(r#"{"version": 2, "program": {}}"#).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "AST format version 2 is not supported, expected 1"
+    );
+}
+
+#[test]
+fn only_arithmetic_compound_assignments_load() {
+    let json = parse("make x = 1; x ^= 2;").to_json();
+    assert!(json.contains(r#""operator":"^""#), "{}", json);
+    assert!(Program::from_json(&json).is_ok());
+
+    for operator in ["&&", "||", "==", "<"] {
+        let json = json.replace(
+            r#""operator":"^""#,
+            &format!(r#""operator":"{}""#, operator),
+        );
+        assert!(Program::from_json(&json).is_err(), "{} loaded", operator);
+    }
+}
@@ -0,0 +1,52 @@
+// the escapes are perfect, nobody gets out unless we say so
+
+use trump::ast::{Expression, Statement};
+use trump::lexer::Lexer;
+use trump::parser::{ParseErrorKind, Parser};
+
+/// Parses a string literal statement and returns its decoded value, or the kinds of the
+/// errors it caused.
+fn decode(literal: &str) -> Result<String, Vec<ParseErrorKind>> {
+    let source = format!("{};", literal);
+    let mut parser = Parser::new(Lexer::new(&source));
+    let mut program = parser.parse_program();
+    if !parser.errors().is_empty() {
+        return Err(parser.errors().iter().map(|e| e.kind).collect());
+    }
+
+    match program.statements.remove(0) {
+        Statement::ExpressionStatement(expr_stmt) => match *expr_stmt.expr {
+            Expression::StringLiteral(string_lit) => Ok(string_lit.value),
+            other => panic!("expected a string literal, got {}", other),
+        },
+        other => panic!("expected an expression statement, got {}", other),
+    }
+}
+
+#[test]
+fn unicode_escapes() {
+    assert_eq!(decode(r#""\u{41}""#), Ok("A".to_string()));
+    assert_eq!(decode(r#""\u{1F600}""#), Ok("\u{1F600}".to_string()));
+    assert_eq!(decode(r#""\u{10ffff}""#), Ok("\u{10FFFF}".to_string()));
+}
+
+#[test]
+fn unicode_escapes_only_take_hex_digits() {
+    for literal in [
+        r#""\u{+41}""#,
+        r#""\u{-41}""#,
+        r#""\u{ 41}""#,
+        r#""\u{4_1}""#,
+        r#""\u{}""#,
+        r#""\u{1234567}""#,
+        r#""\u{110000}""#,
+        r#""\u{D800}""#,
+    ] {
+        assert_eq!(
+            decode(literal),
+            Err(vec![ParseErrorKind::InvalidEscape]),
+            "{}",
+            literal
+        );
+    }
+}
@@ -0,0 +1,28 @@
+// the interpreter gets the right answer, every time, everybody says so
+
+use trump::interpreter::{Interpreter, Value};
+use trump::lexer::Lexer;
+use trump::parser::Parser;
+
+fn eval(source: &str) -> Value {
+    let mut parser = Parser::new(Lexer::new(source));
+    let program = parser.parse_program();
+    assert!(parser.errors().is_empty(), "{:?}", parser.errors());
+
+    Interpreter::new()
+        .eval_program(&program)
+        .unwrap_or_else(|error| panic!("{}", error))
+}
+
+#[test]
+fn integers_and_floats_compare_equal_inside_arrays_and_maps() {
+    assert_eq!(eval("1 == 1.0;"), Value::Boolean(true));
+    assert_eq!(eval("[1] == [1.0];"), Value::Boolean(true));
+    assert_eq!(eval("[1.0, [2]] == [1, [2.0]];"), Value::Boolean(true));
+    assert_eq!(
+        eval(r#"make m = {"a": 1}; m == {"a": 1.0};"#),
+        Value::Boolean(true)
+    );
+    assert_eq!(eval("[1] != [1.0];"), Value::Boolean(false));
+    assert_eq!(eval("[1] == [1.5];"), Value::Boolean(false));
+}
@@ -0,0 +1,98 @@
+// the lexer reads everything, it reads it very well, nobody has ever seen reading like it
+
+use trump::lexer::{LexErrorKind, Lexer};
+use trump::token::TokenType;
+
+fn token_types(source: &str) -> Vec<TokenType> {
+    Lexer::new(source).map(|token| token.token_type).collect()
+}
+
+fn error_kinds(source: &str) -> Vec<LexErrorKind> {
+    let mut lexer = Lexer::new(source);
+    lexer.by_ref().for_each(drop);
+    lexer.errors().iter().map(|error| error.kind).collect()
+}
+
+#[test]
+fn unclosed_interpolation_is_reported_at_its_brace() {
+    let source = r#""a { b"; print(1);"#;
+    let mut lexer = Lexer::new(source);
+    let tokens: Vec<_> = lexer.by_ref().collect();
+
+    let [error] = lexer.errors() else {
+        panic!("expected one error, got {:?}", lexer.errors());
+    };
+    assert_eq!(error.kind, LexErrorKind::UnclosedInterpolation);
+    assert_eq!(&source[error.span.start..error.span.end], "{");
+    assert_eq!(error.span.column, 3);
+
+    assert_eq!(tokens[0].token_type, TokenType::Illegal);
+    assert_eq!(tokens[0].lexeme, r#""a { b""#);
+    assert_eq!(
+        tokens[1..].iter().map(|t| t.token_type).collect::<Vec<_>>(),
+        [
+            TokenType::SemiColon,
+            TokenType::Identifier,
+            TokenType::LeftParen,
+            TokenType::Integer,
+            TokenType::RightParen,
+            TokenType::SemiColon,
+            TokenType::Eof,
+        ]
+    );
+}
+
+#[test]
+fn unclosed_interpolation_after_a_closed_one() {
+    let source = r#""{f("x")} {b"; 1;"#;
+    let mut lexer = Lexer::new(source);
+    lexer.by_ref().for_each(drop);
+
+    let [error] = lexer.errors() else {
+        panic!("expected one error, got {:?}", lexer.errors());
+    };
+    assert_eq!(error.kind, LexErrorKind::UnclosedInterpolation);
+    assert_eq!(error.span.start, source.rfind('{').unwrap());
+}
+
+#[test]
+fn every_unclosed_interpolation_is_reported() {
+    assert_eq!(
+        error_kinds(r#""a { b"; "c { d"; 1;"#),
+        [
+            LexErrorKind::UnclosedInterpolation,
+            LexErrorKind::UnclosedInterpolation
+        ]
+    );
+}
+
+#[test]
+fn escaped_brace_is_not_an_interpolation() {
+    assert_eq!(
+        token_types(r#""a \{ b"; 1;"#),
+        [
+            TokenType::String,
+            TokenType::SemiColon,
+            TokenType::Integer,
+            TokenType::SemiColon,
+            TokenType::Eof,
+        ]
+    );
+}
+
+#[test]
+fn nested_strings_in_interpolations() {
+    assert_eq!(
+        token_types(r#""a {f("b {g("c")}")} d";"#),
+        [TokenType::String, TokenType::SemiColon, TokenType::Eof]
+    );
+}
+
+#[test]
+fn missing_closing_quote_is_still_unterminated() {
+    assert_eq!(
+        error_kinds(r#""a {f("b")} c"#),
+        [LexErrorKind::UnterminatedString]
+    );
+    assert_eq!(error_kinds(r#""a"#), [LexErrorKind::UnterminatedString]);
+}
@@ -0,0 +1,52 @@
+// nobody nests deeper than me, but even I know when to stop
+
+use trump::lexer::Lexer;
+use trump::parser::{ParseErrorKind, Parser, MAX_NESTING_DEPTH};
+
+fn nesting_errors(source: &str) -> usize {
+    let mut parser = Parser::new(Lexer::new(source));
+    parser.parse_program();
+    parser
+        .errors()
+        .iter()
+        .filter(|e| e.kind == ParseErrorKind::NestingTooDeep)
+        .count()
+}
+
+fn parens(depth: usize) -> String {
+    format!("{}1{};", "(".repeat(depth), ")".repeat(depth))
+}
+
+#[test]
+fn nesting_up_to_the_limit_parses() {
+    let source = parens(MAX_NESTING_DEPTH - 1);
+    let mut parser = Parser::new(Lexer::new(&source));
+    parser.parse_program();
+    assert!(parser.errors().is_empty(), "{:?}", parser.errors());
+}
+
+#[test]
+fn deep_parens_are_a_parse_error() {
+    assert_eq!(nesting_errors(&parens(MAX_NESTING_DEPTH + 1)), 1);
+    assert_eq!(nesting_errors(&parens(20_000)), 1);
+}
+
+#[test]
+fn deep_blocks_are_a_parse_error() {
+    let source = format!(
+        "let x = {}1{}; print(x);",
+        "if true { ".repeat(5_000),
+        " }".repeat(5_000)
+    );
+    assert_eq!(nesting_errors(&source), 1);
+}
+
+#[test]
+fn deep_interpolations_are_a_parse_error() {
+    let source = format!(
+        "print(\"{}x{}\");",
+        "{\"".repeat(5_000),
+        "\"}".repeat(5_000)
+    );
+    assert_eq!(nesting_errors(&source), 1);
+}
@@ -0,0 +1,70 @@
+// every node knows exactly where it came from, perfect spans
+
+use trump::ast::{Expression, Statement};
+use trump::lexer::Lexer;
+use trump::parser::Parser;
+
+/// Parses a single expression statement and returns its expression.
+fn parse_expression(source: &str) -> Expression {
+    let mut parser = Parser::new(Lexer::new(source));
+    let mut program = parser.parse_program();
+    assert!(parser.errors().is_empty(), "{:?}", parser.errors());
+
+    match program.statements.remove(0) {
+        Statement::ExpressionStatement(expr_stmt) => *expr_stmt.expr,
+        other => panic!("expected an expression statement, got {}", other),
+    }
+}
+
+fn text<'a>(source: &'a str, expr: &Expression) -> &'a str {
+    &source[expr.span().start..expr.span().end]
+}
+
+#[test]
+fn infix_with_grouped_left_operand() {
+    let source = "(1 + 2) * 3;";
+    let expr = parse_expression(source);
+    assert_eq!(text(source, &expr), "(1 + 2) * 3");
+
+    let Expression::InfixExpression(infix_expr) = expr else {
+        panic!("expected an infix expression");
+    };
+    assert_eq!(text(source, &infix_expr.left_node), "(1 + 2)");
+    assert_eq!(text(source, &infix_expr.right_node), "3");
+}
+
+#[test]
+fn call_and_index_with_grouped_left_operand() {
+    let source = "(f)(x);";
+    assert_eq!(text(source, &parse_expression(source)), "(f)(x)");
+
+    let source = "((a))[0];";
+    assert_eq!(text(source, &parse_expression(source)), "((a))[0]");
+}
+
+#[test]
+fn assignment_to_grouped_target() {
+    let source = "(a)[0] += 1;";
+    let expr = parse_expression(source);
+    assert_eq!(text(source, &expr), "(a)[0] += 1");
+
+    let Expression::AssignExpression(assign_expr) = expr else {
+        panic!("expected an assignment");
+    };
+    let target = assign_expr.target.span();
+    assert_eq!(&source[target.start..target.end], "(a)[0]");
+}
+
+#[test]
+fn grouped_expression_on_a_later_line() {
+    let source = "make x = 1;\n  (x + 1) * 2;";
+    let mut parser = Parser::new(Lexer::new(source));
+    let program = parser.parse_program();
+
+    let Statement::ExpressionStatement(ref expr_stmt) = program.statements[1] else {
+        panic!("expected an expression statement");
+    };
+    let span = expr_stmt.expr.span();
+    assert_eq!((span.line, span.column), (2, 2));
+    assert_eq!(&source[span.start..span.end], "(x + 1) * 2");
+}